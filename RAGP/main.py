@@ -5,8 +5,13 @@ import shutil
 import sys
 
 from environment import translate
-from ragp_bootstrap import innate_registry_version, node_pool_full, seed_initial_knowledge
-from ragp_loop import run_survival_loop
+from ragp_bootstrap import (
+    innate_registry_version,
+    node_pool_full,
+    retag_seed_links,
+    seed_initial_knowledge,
+)
+from ragp_loop import node_roles, run_survival_loop
 
 try:
     from ctn_engine import RagpEngine
@@ -102,8 +107,13 @@ def main():
 
     first_init = not base_file_exists(STORAGE_DIR)
     engine = RagpEngine(STORAGE_DIR)
-    migration_status = engine.ensure_innate_registry(node_pool_full())
+    pool = node_pool_full()
+    migration_status = engine.ensure_innate_registry(pool, node_roles(pool))
     print(f"[Registry] {migration_status}")
+    if not first_init:
+        retagged = retag_seed_links(engine)
+        if retagged:
+            print(f"[Migrasi] {retagged} seed link diberi tipe (cost/opportunity/transition)")
     if async_enabled:
         msg = engine.start_async_runtime(None)
         print(f"[Async] {msg}")
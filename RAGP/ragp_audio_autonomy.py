@@ -9,7 +9,8 @@ from pathlib import Path
 import numpy as np
 
 from environment import translate
-from ragp_bootstrap import innate_registry_version, node_pool_full
+from ragp_bootstrap import innate_registry_version, node_pool_full, retag_seed_links
+from ragp_loop import node_roles
 
 try:
     import sounddevice as sd
@@ -123,8 +124,12 @@ def main() -> None:
 
     os.environ.setdefault("RAGP_INNATE_REGISTRY_VERSION", str(innate_registry_version()))
     engine = RagpEngine(args.storage_dir)
-    migration_status = engine.ensure_innate_registry(node_pool_full())
+    pool = node_pool_full()
+    migration_status = engine.ensure_innate_registry(pool, node_roles(pool))
     print(f"[AudioAutonomy] registry: {migration_status}")
+    retagged = retag_seed_links(engine)
+    if retagged:
+        print(f"[AudioAutonomy] {retagged} seed link diberi tipe")
     async_on = False
     if not args.force_sync:
         try:
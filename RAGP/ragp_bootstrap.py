@@ -8,30 +8,32 @@ from typing import Any, Iterable
 
 DEFAULT_REGISTRY_VERSION = 1
 DEFAULT_NODE_MAX = 109
-DEFAULT_SEED_LINKS: tuple[tuple[int, int, float], ...] = (
-    (1, 45, 0.3),     # BAHAYA -> LARI
-    (1, 88, 0.2),     # BAHAYA -> SEMBUNYI
-    (1, 12, 0.1),     # BAHAYA -> DIAM
-    (103, 106, 0.3),  # LAPAR -> CARI_MAKAN
-    (103, 107, 0.2),  # LAPAR -> MAKAN
-    (100, 108, 0.3),  # LELAH -> ISTIRAHAT
-    (100, 109, 0.2),  # LELAH -> TIDUR
-    (104, 109, 0.3),  # SAKIT -> TIDUR
-    (104, 108, 0.2),  # SAKIT -> ISTIRAHAT
-    (45, 100, 0.15),  # Cost LARI
-    (88, 100, 0.05),  # Cost SEMBUNYI
-    (12, 100, 0.02),  # Cost DIAM
-    (106, 100, 0.10), # Cost CARI_MAKAN
-    (107, 103, 0.05), # MAKAN menurunkan LAPAR
-    (101, 88, 0.2),   # MALAM -> SEMBUNYI
-    (101, 45, 0.05),  # MALAM -> LARI rendah
+# Jenis sinapsis: association (nilai), cost, opportunity, transition (prediksi).
+SYNAPSE_KINDS = ("association", "cost", "opportunity", "transition")
+DEFAULT_SEED_LINKS: tuple[tuple[int, int, float, str], ...] = (
+    (1, 45, 0.3, "association"),      # BAHAYA -> LARI
+    (1, 88, 0.2, "association"),      # BAHAYA -> SEMBUNYI
+    (1, 12, 0.1, "association"),      # BAHAYA -> DIAM
+    (103, 106, 0.3, "association"),   # LAPAR -> CARI_MAKAN
+    (103, 107, 0.2, "association"),   # LAPAR -> MAKAN
+    (100, 108, 0.3, "association"),   # LELAH -> ISTIRAHAT
+    (100, 109, 0.2, "association"),   # LELAH -> TIDUR
+    (104, 109, 0.3, "association"),   # SAKIT -> TIDUR
+    (104, 108, 0.2, "association"),   # SAKIT -> ISTIRAHAT
+    (45, 100, 0.15, "cost"),          # Cost LARI
+    (88, 100, 0.05, "cost"),          # Cost SEMBUNYI
+    (12, 100, 0.02, "cost"),          # Cost DIAM
+    (106, 100, 0.10, "cost"),         # Cost CARI_MAKAN
+    (107, 103, 0.05, "transition"),   # MAKAN menurunkan LAPAR
+    (101, 88, 0.2, "opportunity"),    # MALAM -> SEMBUNYI
+    (101, 45, 0.05, "opportunity"),   # MALAM -> LARI rendah
 )
 DEFAULT_BOOTSTRAP: dict[str, Any] = {
     "registry_version": DEFAULT_REGISTRY_VERSION,
     "node_max": DEFAULT_NODE_MAX,
     "seed_links": [
-        {"sender": s, "receiver": r, "weight": w}
-        for s, r, w in DEFAULT_SEED_LINKS
+        {"sender": s, "receiver": r, "weight": w, "kind": k}
+        for s, r, w, k in DEFAULT_SEED_LINKS
     ],
 }
 
@@ -95,33 +97,58 @@ def node_pool_full() -> list[int]:
     return list(range(1, max_node + 1))
 
 
-def seed_links() -> list[tuple[int, int, float]]:
+def seed_links() -> list[tuple[int, int, float, str]]:
     cfg = _load_bootstrap_config()
     raw_links = cfg.get("seed_links", DEFAULT_BOOTSTRAP["seed_links"])
     if not isinstance(raw_links, list):
         raw_links = DEFAULT_BOOTSTRAP["seed_links"]
 
-    out: list[tuple[int, int, float]] = []
+    out: list[tuple[int, int, float, str]] = []
     for item in raw_links:
         if not isinstance(item, dict):
             continue
         sender = _safe_int(item.get("sender"), 0)
         receiver = _safe_int(item.get("receiver"), 0)
         weight = _safe_float(item.get("weight"), 0.0)
+        kind = str(item.get("kind", "association")).strip().lower()
         if sender <= 0 or receiver <= 0:
             continue
-        out.append((sender, receiver, max(0.0, min(1.0, weight))))
+        if kind not in SYNAPSE_KINDS:
+            kind = "association"
+        out.append((sender, receiver, max(-1.0, min(1.0, weight)), kind))
 
     if out:
         return out
     return list(DEFAULT_SEED_LINKS)
 
 
+def retag_seed_links(engine) -> int:
+    """Give typed seed links their kind in stores seeded before synapses were typed.
+
+    A v1 store reads every edge as an association, so its cost/opportunity/transition
+    seeds would never reach the right Cd term. Only edges still tagged association are
+    retagged, and their current (possibly learned) weight is kept.
+    """
+    retagged = 0
+    for sender, receiver, _, kind in seed_links():
+        if kind == "association":
+            continue
+        try:
+            edges = engine.get_typed_connections(sender)
+        except ValueError:
+            continue
+        for target, _, current in edges:
+            if target == receiver and current == "association":
+                engine.retype_edge(sender, receiver, kind)
+                retagged += 1
+    return retagged
+
+
 def seed_initial_knowledge(engine) -> None:
     print("[Init] Menanamkan insting dasar...")
-    links: Iterable[tuple[int, int, float]] = seed_links()
-    for sender, receiver, weight in links:
-        engine.update_weight(sender, receiver, weight)
+    links: Iterable[tuple[int, int, float, str]] = seed_links()
+    for sender, receiver, weight, kind in links:
+        engine.update_weight(sender, receiver, weight, kind)
 
     merged, pruned = engine.consolidate()
     print(f"[Init] Insting dasar tersimpan. merged={merged} pruned={pruned}")
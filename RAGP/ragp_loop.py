@@ -1,6 +1,6 @@
 from __future__ import annotations
 
-from environment import VirtualEnvironment, translate
+from environment import SENSOR_NODES, VirtualEnvironment, translate
 
 # Action nodes (int, aligned with Rust u64 API)
 AKSI_NODES = {45, 88, 12, 106, 107, 108, 109}
@@ -9,6 +9,30 @@ AKSI_NODES = {45, 88, 12, 106, 107, 108, 109}
 PRIORITAS_STIMULUS = [104, 1, 103, 100]
 KONTEKS_NODES = {101, 102, 105}
 
+# Homeostatic drives: name -> (sensor node, env attribute); level = 1 - attribute
+DRIVE_SENSOR = {"lapar": (103, "lapar"), "lelah": (100, "lelah"), "sakit": (104, "health")}
+
+# Semantic name prefix -> engine node role.
+_PREFIX_ROLES = {"SENSOR_": "sensor", "KONTEKS_": "context", "AKSI_": "action"}
+
+
+def node_roles(node_ids) -> dict[int, str]:
+    """Engine roles for registered nodes, from the sets above and semantic names."""
+    roles: dict[int, str] = {}
+    for node_id in node_ids:
+        node_id = int(node_id)
+        name = SENSOR_NODES.get(str(node_id), "")
+        for prefix, role in _PREFIX_ROLES.items():
+            if name.startswith(prefix):
+                roles[node_id] = role
+        if node_id in AKSI_NODES:
+            roles[node_id] = "action"
+        elif node_id in PRIORITAS_STIMULUS:
+            roles[node_id] = "sensor"
+        elif node_id in KONTEKS_NODES:
+            roles[node_id] = "context"
+    return roles
+
 
 def _engine_async_on(engine) -> bool:
     try:
@@ -30,7 +54,10 @@ def run_survival_loop(
     Main survival loop.
 
     Engine contract:
-    - compute_cd(stimulus: int, context: list[int]) -> list[tuple[int, float]]
+    - select_action(stimuli: list[int], context: list[int]) -> dict
+    - set_drive(name: str, level: float, node: int)
+    - apply_reward(reward: float, nodes: list[int], terminal: bool) -> dict
+    - compute_cd_explain(stimulus, context, allowed) -> dict (optional, for logging)
     - spread_activation(seed_node: int, seed_strength: float)
     - form_synapses_from_window() -> int
     """
@@ -43,6 +70,7 @@ def run_survival_loop(
 
         # Environment provides string sensor IDs; convert to int for Rust engine.
         sensors = [int(s) for s in env.get_active_sensors()]
+        _perbarui_drive(engine, env)
 
         if _engine_async_on(engine):
             batch = [(int(sensor), 1.0, "survival_loop") for sensor in sensors]
@@ -57,21 +85,30 @@ def run_survival_loop(
             aksi_id = 108
             alasan = "no urgent stimulus -> ISTIRAHAT"
         else:
-            hasil_cd = engine.compute_cd(stimulus, context)
-            hasil_cd = [(a, cd) for a, cd in hasil_cd if a in AKSI_NODES]
-            if not hasil_cd:
+            # Urgent stimuli compete together; the engine masks non-action receivers
+            # once node roles are registered.
+            stimuli = [p for p in PRIORITAS_STIMULUS if p in sensors]
+            seleksi = engine.select_action(stimuli, context)
+            if seleksi["winner"] is None:
                 aksi_id = 108
-                alasan = f"no valid action for stimulus {translate(stimulus)}"
+                alasan = (
+                    f"no valid action for stimulus {translate(stimulus)} "
+                    f"({seleksi['mode']})"
+                )
             else:
-                aksi_id = hasil_cd[0][0]
+                aksi_id = seleksi["winner"]
+                stimulus = seleksi["winner_stimulus"]
                 alasan = (
                     f"stimulus={translate(stimulus)} "
                     f"context={[translate(c) for c in context]} "
-                    f"Cd={hasil_cd[0][1]:.3f}"
+                    f"Cd={seleksi['winner_cd']:.3f} [{seleksi['mode']}]"
+                    f"{_rincian_cd(engine, stimulus, context, aksi_id)}"
                 )
 
         result = env.apply_action(str(aksi_id))
         reward = float(result["reward"])
+        # TD(lambda): credits earlier steps (e.g. CARI_MAKAN -> MAKAN -> relief).
+        engine.apply_reward(reward, sensors + [aksi_id], env.gugur)
 
         formed = int(engine.form_synapses_from_window())
 
@@ -95,6 +132,30 @@ def run_survival_loop(
     return env
 
 
+def _perbarui_drive(engine, env) -> None:
+    """Push current hunger/fatigue/pain levels into the engine's drive state."""
+    for nama, (node, atribut) in DRIVE_SENSOR.items():
+        level = 1.0 - float(getattr(env, atribut))
+        engine.set_drive(nama, min(max(level, 0.0), 1.0), node)
+
+
+def _rincian_cd(engine, stimulus: int, context: list[int], aksi_id: int) -> str:
+    """Value/opportunity/cost of the chosen action; '*' marks a default component."""
+    try:
+        rincian = engine.compute_cd_explain(stimulus, context, sorted(AKSI_NODES))
+    except Exception:
+        return ""
+    for row in rincian.get("actions", []):
+        if row["action"] == aksi_id:
+            o_def = "*" if row["opportunity_default"] else ""
+            c_def = "*" if row["cost_default"] else ""
+            return (
+                f" (V={row['value']:.3f} O={row['opportunity']:.3f}{o_def} "
+                f"C={row['cost']:.3f}{c_def})"
+            )
+    return ""
+
+
 def _parse_sensors(sensors: list[int]) -> tuple[int | None, list[int]]:
     stimulus = None
     context: list[int] = []
@@ -2,14 +2,15 @@
 use pyo3::exceptions::PyValueError;
 use pyo3::prelude::*;
 use pyo3::types::{PyAny, PyDict};
-use std::collections::{HashMap, HashSet, VecDeque};
+use std::cell::RefCell;
+use std::collections::{BTreeMap, HashMap, HashSet, VecDeque};
 use std::env;
 use std::fs::{self, File, OpenOptions};
-use std::io::{Read, Seek, SeekFrom, Write};
+use std::io::{Cursor, Read, Seek, SeekFrom, Write};
 use std::num::NonZeroUsize;
 use std::path::PathBuf;
 use std::sync::atomic::{AtomicU64, Ordering};
-use std::sync::Arc;
+use std::sync::{Arc, Mutex, PoisonError};
 
 use lru::LruCache;
 use sysinfo::System;
@@ -18,23 +19,84 @@ use tokio::sync::{mpsc, oneshot, Mutex as TokioMutex};
 
 const MAGIC_BASE: u32 = 0x5241_4750; // "RAGP"
 const MAGIC_DELTA: u32 = 0x4445_4C54; // "DELT"
+const MAGIC_VALUES: u32 = 0x5641_4C55; // "VALU"
 const VERSION: u16 = 1;
-
-const BASE_HEADER_SIZE: u64 = 14;
+// Base manifest and delta log format: v2 adds a synapse kind byte to every record.
+// v1 files (12-byte synapses, 28-byte delta entries) still load as association edges.
+// Base v3 adds per-synapse history and the tick of the last consolidation; v1/v2
+// synapses load with unknown (zero) history.
+const BASE_VERSION: u16 = 3;
+const DELTA_VERSION: u16 = 2;
+// values.bin (learned TD node values) is versioned on its own.
+const VALUES_VERSION: u16 = 1;
+
+const BASE_HEADER_SIZE: u64 = 18;
+const BASE_HEADER_SIZE_V2: u64 = 14;
 const NODE_INDEX_SIZE: u64 = 32;
-const SYNAPSE_SIZE: u64 = 12;
+const SYNAPSE_SIZE: u64 = 28;
+const SYNAPSE_SIZE_V2: u64 = 16;
+const SYNAPSE_SIZE_V1: u64 = 12;
 const DELTA_HEADER_SIZE: u64 = 8;
-const DELTA_ENTRY_SIZE: u64 = 28;
-const CHUNK_SPAN: u64 = 100;
+const DELTA_ENTRY_SIZE: u64 = 32;
+const DELTA_ENTRY_SIZE_V1: u64 = 28;
+const DEFAULT_CHUNK_SPAN: u64 = 100;
 const OFFSET_CHUNK_FLAG: u64 = 1_u64 << 63;
 
-const MAX_SYNAPSES_PER_NODE: u32 = 7000;
-const LRU_CAPACITY: usize = 1000;
-const INITIAL_WEIGHT: f32 = 0.01;
+const DEFAULT_MAX_SYNAPSES_PER_NODE: u32 = 7000;
+const DEFAULT_SATURATION_POLICY: &str = "refuse";
+// Delta entry flag byte (offset 25): the entry is a tombstone for an evicted edge.
+const DELTA_FLAG_TOMBSTONE: u8 = 1;
+// The entry is a node record (sender = node, receiver = role code, weight =
+// threshold); together with the tombstone bit it retires the node.
+const DELTA_FLAG_NODE: u8 = 2;
+// Learned nodes (added at runtime, outside the innate registry) take IDs in
+// [START, END); END keeps chunk starts inside the 31 bits `encode_chunk_offset` has.
+const LEARNED_NODE_ID_START: u64 = 1 << 30;
+const LEARNED_NODE_ID_END: u64 = 1 << 31;
+const DEFAULT_LRU_CAPACITY: usize = 1000;
+const DEFAULT_INITIAL_WEIGHT: f32 = 0.01;
 const DEFAULT_THRESHOLD: f32 = 0.2;
-const PRUNE_RATIO: f32 = 0.3;
-const TEMPORAL_WINDOW_SIZE: usize = 5;
-const MAX_SPREAD_DEPTH: u8 = 4;
+const DEFAULT_PRUNE_RATIO: f32 = 0.3;
+const DEFAULT_PRUNE_STRATEGY: &str = "relative_mean";
+const DEFAULT_TEMPORAL_WINDOW_SIZE: usize = 5;
+const DEFAULT_MAX_SPREAD_DEPTH: u8 = 4;
+
+const DEFAULT_ACTIVATION_MODE: &str = "reset";
+const DEFAULT_ACTIVATION_DECAY: f32 = 0.2;
+const DEFAULT_ACTIVATION_FLOOR: f32 = 0.001;
+const DEFAULT_INTEGRATION: &str = "max";
+const DEFAULT_SPREAD_DYNAMICS: &str = "bfs";
+const DEFAULT_CD_COST: f64 = 1.0;
+const DEFAULT_CD_OPPORTUNITY: f64 = 0.5;
+/// Default cost floor for Cd: zero-weight cost edges give a large but finite,
+/// signed score (100x the unfloored formula at cost 0.01).
+const DEFAULT_CD_MIN_COST: f32 = 0.01;
+const DEFAULT_SETTLE_EPSILON: f32 = 0.001;
+const DEFAULT_SETTLE_MAX_ITERS: u32 = 50;
+const DEFAULT_SELECT_INHIBITION: f32 = 0.5;
+const DEFAULT_SELECT_GO_THRESHOLD: f32 = 0.05;
+const DEFAULT_SELECT_ITERS: u32 = 20;
+const SELECT_EPSILON: f32 = 1e-4;
+const DEFAULT_PLAN_HORIZON: u32 = 3;
+const DEFAULT_PLAN_BEAM_WIDTH: u32 = 4;
+const DEFAULT_CONCEPT_SUPPORT: u32 = 8;
+const DEFAULT_CONCEPT_SIZE: usize = 3;
+// Strongest co-active nodes per observation whose subsets are counted (bounds the work).
+const CONCEPT_CANDIDATE_NODES: usize = 8;
+// Candidate sets tracked before those seen only once are forgotten.
+const CONCEPT_CANDIDATE_CAP: usize = 4096;
+const CONCEPT_LINK_WEIGHT: f32 = 0.5;
+const DEFAULT_RECALL_TOP_K: usize = 5;
+// Pseudo-sender for a seed's external input in WaveInputs.
+const EXTERNAL_INPUT: u64 = u64::MAX;
+// Async waves kept for cross-shard hops, counted in origin ticks.
+const ASYNC_WAVE_RETENTION: u64 = 64;
+
+const DEFAULT_LEARNING_MODE: &str = "hebbian";
+const DEFAULT_STDP_A_PLUS: f32 = 0.05;
+const DEFAULT_STDP_A_MINUS: f32 = 0.055;
+const DEFAULT_STDP_TAU_PLUS: f32 = 2.0;
+const DEFAULT_STDP_TAU_MINUS: f32 = 2.0;
 
 const CACHE_RECOMPUTE_ACCESS_INTERVAL: u32 = 500;
 const DEFAULT_CACHE_POLICY: &str = "pinned_lru";
@@ -42,7 +104,19 @@ const DEFAULT_CACHE_RAM_FRACTION: f32 = 0.25;
 const DEFAULT_CACHE_RAM_MIN_MB: u64 = 256;
 const DEFAULT_CACHE_RAM_MAX_MB: u64 = 1536;
 const DEFAULT_CACHE_PIN_FRACTION: f32 = 0.35;
+// Update count at which an edge's reinforcement counts half in the pinned score.
+const PINNED_REINFORCE_HALF: f32 = 4.0;
 const DEFAULT_INNATE_REGISTRY_VERSION: u32 = 1;
+const BASE_FILE_NAME: &str = "base.bin";
+const DELTA_FILE_NAME: &str = "delta.bin";
+const CONFIG_FILE_NAME: &str = "ragp_config.toml";
+const DRIVES_FILE_NAME: &str = "ragp_drives.toml";
+const CONCEPTS_FILE_NAME: &str = "ragp_concepts.toml";
+const VALUES_FILE_NAME: &str = "values.bin";
+const DEFAULT_TD_ALPHA: f32 = 0.1;
+const DEFAULT_TD_GAMMA: f32 = 0.9;
+const DEFAULT_TD_LAMBDA: f32 = 0.8;
+const TD_TRACE_FLOOR: f32 = 1e-4;
 const DEFAULT_ASYNC_RAM_WARN_MB: u64 = 1024;
 const DEFAULT_ASYNC_RAM_CRITICAL_MB: u64 = 1536;
 const DEFAULT_ASYNC_COALESCE_WINDOW_MS: u64 = 300;
@@ -67,8 +141,6 @@ struct AsyncRuntimeState {
     hop_total: u64,
     processed_total: u64,
     processed_per_sec: f64,
-    last_rate_ts_ms: u64,
-    last_rate_processed_total: u64,
     guard_mode: String,
     per_shard_queue_len: Vec<u64>,
     per_shard_processed: Vec<u64>,
@@ -77,31 +149,597 @@ struct AsyncRuntimeState {
 
 #[derive(Clone, Debug)]
 struct NodeMeta {
+    #[allow(dead_code)]
     node_id: u64,
     synapse_count: u32,
     synapse_offset: u64,
     threshold: f32,
     checksum: u32,
+    role: NodeRole,
+    /// TD-learned value estimate (persisted in `values.bin`).
+    value: f32,
+}
+
+/// Functional role of a node, stored in the last u32 of its manifest record
+/// (previously reserved and always 0, which reads back as `Unassigned`).
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+enum NodeRole {
+    #[default]
+    Unassigned,
+    Sensor,
+    Context,
+    Action,
+    Internal,
+    Homeostatic,
+}
+
+impl NodeRole {
+    fn parse(name: &str) -> Result<Self, String> {
+        match name.trim().to_ascii_lowercase().as_str() {
+            "unassigned" | "none" => Ok(NodeRole::Unassigned),
+            "sensor" => Ok(NodeRole::Sensor),
+            "context" => Ok(NodeRole::Context),
+            "action" => Ok(NodeRole::Action),
+            "internal" => Ok(NodeRole::Internal),
+            "homeostatic" => Ok(NodeRole::Homeostatic),
+            other => Err(format!(
+                "role must be one of sensor/context/action/internal/homeostatic/unassigned, got '{}'",
+                other
+            )),
+        }
+    }
+
+    fn name(self) -> &'static str {
+        match self {
+            NodeRole::Unassigned => "unassigned",
+            NodeRole::Sensor => "sensor",
+            NodeRole::Context => "context",
+            NodeRole::Action => "action",
+            NodeRole::Internal => "internal",
+            NodeRole::Homeostatic => "homeostatic",
+        }
+    }
+
+    fn code(self) -> u32 {
+        match self {
+            NodeRole::Unassigned => 0,
+            NodeRole::Sensor => 1,
+            NodeRole::Context => 2,
+            NodeRole::Action => 3,
+            NodeRole::Internal => 4,
+            NodeRole::Homeostatic => 5,
+        }
+    }
+
+    fn from_code(code: u32) -> Self {
+        match code {
+            1 => NodeRole::Sensor,
+            2 => NodeRole::Context,
+            3 => NodeRole::Action,
+            4 => NodeRole::Internal,
+            5 => NodeRole::Homeostatic,
+            _ => NodeRole::Unassigned,
+        }
+    }
+}
+
+/// What an edge means to `compute_cd`: stimulus->action value (`association`),
+/// action effort (`cost`), context support (`opportunity`) or a predicted
+/// action->state outcome (`transition`). Stored as one byte per synapse record.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+enum SynapseKind {
+    #[default]
+    Association,
+    Cost,
+    Opportunity,
+    Transition,
+}
+
+impl SynapseKind {
+    const ALL: [SynapseKind; 4] = [
+        SynapseKind::Association,
+        SynapseKind::Cost,
+        SynapseKind::Opportunity,
+        SynapseKind::Transition,
+    ];
+
+    fn parse(name: &str) -> Result<Self, String> {
+        match name.trim().to_ascii_lowercase().as_str() {
+            "association" | "assoc" => Ok(SynapseKind::Association),
+            "cost" => Ok(SynapseKind::Cost),
+            "opportunity" => Ok(SynapseKind::Opportunity),
+            "transition" => Ok(SynapseKind::Transition),
+            other => Err(format!(
+                "kind must be one of association/cost/opportunity/transition, got '{}'",
+                other
+            )),
+        }
+    }
+
+    fn name(self) -> &'static str {
+        match self {
+            SynapseKind::Association => "association",
+            SynapseKind::Cost => "cost",
+            SynapseKind::Opportunity => "opportunity",
+            SynapseKind::Transition => "transition",
+        }
+    }
+
+    fn code(self) -> u8 {
+        match self {
+            SynapseKind::Association => 0,
+            SynapseKind::Cost => 1,
+            SynapseKind::Opportunity => 2,
+            SynapseKind::Transition => 3,
+        }
+    }
+
+    fn from_code(code: u8) -> Self {
+        match code {
+            1 => SynapseKind::Cost,
+            2 => SynapseKind::Opportunity,
+            3 => SynapseKind::Transition,
+            _ => SynapseKind::Association,
+        }
+    }
 }
 
 #[derive(Clone, Debug)]
 struct Synapse {
     receiver_id: u64,
     weight: f32,
+    kind: SynapseKind,
+    /// Tick of the first write (0 when unknown, i.e. loaded from a pre-v3 base).
+    created_at: u32,
+    /// Tick of the latest write.
+    last_updated: u32,
+    /// Writes seen over the edge's lifetime; 0 means the history is unknown.
+    update_count: u32,
+}
+
+impl Synapse {
+    fn new(receiver_id: u64, weight: f32, kind: SynapseKind) -> Self {
+        Synapse {
+            receiver_id,
+            weight,
+            kind,
+            created_at: 0,
+            last_updated: 0,
+            update_count: 0,
+        }
+    }
+
+    fn has_history(&self) -> bool {
+        self.update_count > 0
+    }
 }
 
+/// Merged (receiver, weight, kind) edges of one sender.
+type TypedConnections = Vec<(u64, f32, SynapseKind)>;
+
 #[derive(Clone, Debug)]
 struct AsyncSynapse {
     receiver_id: u64,
     weight: f32,
 }
 
+/// How activation evolves between seeds; shared by the sync and async paths.
+#[derive(Clone, Debug)]
+struct ActivationDynamics {
+    /// "reset": every seed starts from an empty map; "accumulate": levels persist and decay.
+    mode: String,
+    /// Fraction of activation lost per tick.
+    decay: f32,
+    /// Levels below this are dropped after decay.
+    floor: f32,
+    /// How convergent excitatory inputs to one node combine within a wave.
+    integration: Integration,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Integration {
+    /// Strongest single input wins (legacy behavior).
+    Max,
+    /// Inputs add up, capped at 1.0.
+    Sum,
+    /// Inputs add up through a saturating `tanh`.
+    SigmoidSum,
+}
+
+impl Integration {
+    fn parse(name: &str) -> Result<Self, String> {
+        match name.trim().to_ascii_lowercase().as_str() {
+            "max" => Ok(Integration::Max),
+            "sum" => Ok(Integration::Sum),
+            "sigmoid_sum" => Ok(Integration::SigmoidSum),
+            other => Err(format!(
+                "integration must be 'max', 'sum' or 'sigmoid_sum', got '{}'",
+                other
+            )),
+        }
+    }
+
+    fn combine(&self, inputs: &HashMap<u64, f32>) -> f32 {
+        match self {
+            Integration::Max => inputs.values().copied().fold(0.0_f32, f32::max),
+            Integration::Sum => inputs.values().sum::<f32>().min(1.0),
+            Integration::SigmoidSum => inputs.values().sum::<f32>().tanh(),
+        }
+    }
+}
+
+/// Provenance of a node's current activation, recorded in trace mode: the input
+/// whose arrival set the node's current level.
+#[derive(Clone, Debug)]
+struct ActivationTrace {
+    /// `None` for seeds (external input).
+    predecessor: Option<u64>,
+    /// Synapse weight (external strength for seeds).
+    weight: f32,
+    incoming: f32,
+    level: f32,
+    depth: u8,
+    tick: u64,
+    /// Async only: shard that delivered the input.
+    shard: Option<usize>,
+    /// Async only: the input was forwarded to another shard as a `Hop`.
+    via_hop: bool,
+}
+
+impl ActivationTrace {
+    fn seed(strength: f32, level: f32, tick: u64, shard: Option<usize>) -> Self {
+        ActivationTrace {
+            predecessor: None,
+            weight: strength,
+            incoming: strength,
+            level,
+            depth: 0,
+            tick,
+            shard,
+            via_hop: false,
+        }
+    }
+}
+
+/// Walks predecessors from `node` back to its seed. Returns the path in
+/// seed-to-node order and whether a seed was reached (a later wave can
+/// overwrite an upstream entry and break or loop the chain).
+fn trace_path(trace: &HashMap<u64, ActivationTrace>, node: u64) -> (Vec<(u64, ActivationTrace)>, bool) {
+    let mut path = Vec::new();
+    let mut seen = HashSet::new();
+    let mut current = Some(node);
+    let mut complete = false;
+    while let Some(id) = current {
+        if !seen.insert(id) {
+            break;
+        }
+        let Some(entry) = trace.get(&id) else {
+            break;
+        };
+        path.push((id, entry.clone()));
+        current = entry.predecessor;
+        if current.is_none() {
+            complete = true;
+        }
+    }
+    path.reverse();
+    (path, complete)
+}
+
+/// Per-wave input bookkeeping. Inputs are keyed by sender so a node that is
+/// re-propagated at a higher level replaces its earlier contribution instead of
+/// being counted twice.
+#[derive(Debug, Default)]
+struct WaveInputs {
+    excitatory: HashMap<u64, HashMap<u64, f32>>,
+    inhibitory: HashMap<u64, HashMap<u64, f32>>,
+}
+
+impl WaveInputs {
+    fn inhibition_of(&self, receiver: u64) -> f32 {
+        self.inhibitory
+            .get(&receiver)
+            .map_or(0.0, |m| m.values().sum::<f32>())
+    }
+
+    /// Injects a seed's external input; seeds always fire at least at their strength.
+    fn seed(
+        &mut self,
+        activation: &mut HashMap<u64, f32>,
+        node: u64,
+        strength: f32,
+        integration: Integration,
+    ) -> f32 {
+        let slot = self
+            .excitatory
+            .entry(node)
+            .or_default()
+            .entry(EXTERNAL_INPUT)
+            .or_insert(0.0);
+        *slot = slot.max(strength);
+        let external = *slot;
+        // The external drive itself is never squashed by the integration function.
+        let net = integration.combine(&self.excitatory[&node]) + self.inhibition_of(node);
+        let level = activation
+            .get(&node)
+            .copied()
+            .unwrap_or(0.0)
+            .max(net)
+            .max(external);
+        activation.insert(node, level);
+        level
+    }
+
+    /// Applies one synaptic input. Excitatory input fires the receiver when the
+    /// integrated input, net of inhibition received in this wave, clears the
+    /// threshold and beats the current level. Inhibitory input lowers the current
+    /// level (removing it at zero) and damps later excitation in the same wave.
+    /// Returns the new level when the receiver should keep propagating.
+    fn integrate(
+        &mut self,
+        activation: &mut HashMap<u64, f32>,
+        sender: u64,
+        receiver: u64,
+        incoming: f32,
+        threshold: f32,
+        integration: Integration,
+    ) -> Option<f32> {
+        if incoming < 0.0 {
+            let old = self
+                .inhibitory
+                .entry(receiver)
+                .or_default()
+                .insert(sender, incoming)
+                .unwrap_or(0.0);
+            let delta = incoming - old;
+            if let Some(level) = activation.get_mut(&receiver) {
+                *level += delta;
+                if *level <= 0.0 {
+                    activation.remove(&receiver);
+                }
+            }
+            return None;
+        }
+
+        let inputs = self.excitatory.entry(receiver).or_default();
+        let slot = inputs.entry(sender).or_insert(0.0);
+        *slot = slot.max(incoming);
+        let net = integration.combine(inputs) + self.inhibition_of(receiver);
+        if net < threshold {
+            return None;
+        }
+        let current = activation.get(&receiver).copied().unwrap_or(0.0);
+        if net > current {
+            activation.insert(receiver, net);
+            Some(net)
+        } else {
+            None
+        }
+    }
+}
+
+/// Outgoing edges of one sender as `(receiver, weight, receiver threshold)`.
+type WaveEdges = Vec<(u64, f32, f32)>;
+
+/// What one spread or settle wave reports to its caller, in causal order. The
+/// live engine turns these into traces and temporal-window entries; read-only
+/// queries keep what they need or drop them.
+#[derive(Clone, Debug)]
+enum WaveEvent {
+    /// A seed was injected; `strength` is its external input.
+    Seed { node: u64, strength: f32, level: f32 },
+    /// `node` fired at `level`. `via` is its strongest excitatory sender with the
+    /// edge weight and input it carried (`None` when settling left it no input).
+    Fired { node: u64, via: Option<(u64, f32, f32)>, level: f32, depth: u8 },
+    /// Inhibition drove an active `node` to zero.
+    Silenced { node: u64 },
+}
+
+/// One BFS wave over `activation`: seeds fire at least at their strength, then
+/// every fired node propagates up to `max_depth` hops. `edges` supplies each
+/// sender's synapses the first time it propagates.
+fn spread_wave(
+    activation: &mut HashMap<u64, f32>,
+    seeds: &[(u64, f32)],
+    integration: Integration,
+    max_depth: u8,
+    mut edges: impl FnMut(u64) -> WaveEdges,
+    mut sink: impl FnMut(WaveEvent),
+) {
+    let mut wave = WaveInputs::default();
+    let mut queue: VecDeque<(u64, f32, u8)> = VecDeque::new();
+    for &(node, strength) in seeds {
+        let level = wave.seed(activation, node, strength, integration);
+        sink(WaveEvent::Seed { node, strength, level });
+        queue.push_back((node, level, 0));
+    }
+
+    while let Some((node, strength, depth)) = queue.pop_front() {
+        if depth >= max_depth {
+            continue;
+        }
+        for (receiver, weight, threshold) in edges(node) {
+            let incoming = strength * weight;
+            let was_active = activation.contains_key(&receiver);
+            let fired =
+                wave.integrate(activation, node, receiver, incoming, threshold, integration);
+            if was_active && !activation.contains_key(&receiver) {
+                sink(WaveEvent::Silenced { node: receiver });
+            }
+            if let Some(level) = fired {
+                let depth = depth.saturating_add(1);
+                let via = Some((node, weight, incoming));
+                sink(WaveEvent::Fired { node: receiver, via, level, depth });
+                queue.push_back((receiver, level, depth));
+            }
+        }
+    }
+}
+
+/// Recurrent settling over `activation`: synchronous updates over the active set
+/// until the largest change drops below `epsilon` or `max_iters` is reached.
+/// Seeds are clamped to at least their external strength; every other node is
+/// recomputed from its integrated input each iteration, so cycles are visited
+/// consistently instead of being cut by BFS order and depth. Levels already in
+/// `activation` (accumulate mode) persist as a floor that only inhibition lowers.
+/// Emits the seeds, then the nodes that fired in first-firing order.
+fn settle_wave(
+    activation: &mut HashMap<u64, f32>,
+    seeds: &[(u64, f32)],
+    integration: Integration,
+    max_iters: u32,
+    epsilon: f32,
+    mut edges: impl FnMut(u64) -> WaveEdges,
+    mut sink: impl FnMut(WaveEvent),
+) -> SettleReport {
+    let external: HashMap<u64, f32> = seeds.iter().fold(HashMap::new(), |mut acc, &(n, v)| {
+        let slot = acc.entry(n).or_insert(0.0_f32);
+        *slot = slot.max(v.clamp(0.0, 1.0));
+        acc
+    });
+    let residual: HashMap<u64, f32> = activation
+        .iter()
+        .filter(|(n, _)| !external.contains_key(n))
+        .map(|(n, v)| (*n, *v))
+        .collect();
+    for (node, strength) in &external {
+        let slot = activation.entry(*node).or_insert(0.0);
+        *slot = slot.max(*strength);
+    }
+
+    let mut adjacency: HashMap<u64, WaveEdges> = HashMap::new();
+    let mut thresholds: HashMap<u64, f32> = HashMap::new();
+    let mut first_seen: HashMap<u64, u32> = HashMap::new();
+    let mut report = SettleReport::default();
+    let mut last_inputs: HashMap<u64, HashMap<u64, f32>> = HashMap::new();
+
+    for iter in 1..=max_iters {
+        let mut senders: Vec<u64> = activation.keys().copied().collect();
+        senders.sort_unstable();
+
+        let mut excitatory: HashMap<u64, HashMap<u64, f32>> = HashMap::new();
+        let mut inhibitory: HashMap<u64, f32> = HashMap::new();
+        for sender in senders {
+            let level = activation.get(&sender).copied().unwrap_or(0.0);
+            if level <= 0.0 {
+                continue;
+            }
+            let out = adjacency.entry(sender).or_insert_with(|| edges(sender));
+            for &(receiver, weight, threshold) in out.iter() {
+                thresholds.insert(receiver, threshold);
+                let incoming = level * weight;
+                if incoming < 0.0 {
+                    *inhibitory.entry(receiver).or_insert(0.0) += incoming;
+                } else {
+                    excitatory.entry(receiver).or_default().insert(sender, incoming);
+                }
+            }
+        }
+
+        let mut next: HashMap<u64, f32> = HashMap::new();
+        let mut candidates: HashSet<u64> = excitatory.keys().copied().collect();
+        candidates.extend(external.keys().copied());
+        candidates.extend(residual.keys().copied());
+        for node in candidates {
+            let inhibition = inhibitory.get(&node).copied().unwrap_or(0.0);
+            let net = excitatory
+                .get(&node)
+                .map_or(0.0, |inputs| integration.combine(inputs))
+                + inhibition;
+            let (level, fired) = match external.get(&node) {
+                Some(ext) => (net.max(*ext), true),
+                None => {
+                    // Without excitatory input net <= 0, so the threshold is moot.
+                    let threshold = thresholds.get(&node).copied().unwrap_or(0.0);
+                    let fired = if net < threshold { 0.0 } else { net.min(1.0) };
+                    let carried = residual.get(&node).map_or(0.0, |r| (r + inhibition).max(0.0));
+                    (fired.max(carried), fired > 0.0)
+                }
+            };
+            if level > 0.0 {
+                next.insert(node, level);
+            }
+            if fired {
+                first_seen.entry(node).or_insert(iter);
+            }
+        }
+
+        let mut delta = 0.0_f32;
+        for (node, level) in &next {
+            let prev = activation.get(node).copied().unwrap_or(0.0);
+            delta = delta.max((level - prev).abs());
+        }
+        for (node, prev) in activation.iter() {
+            if !next.contains_key(node) {
+                delta = delta.max(prev.abs());
+            }
+        }
+
+        *activation = next;
+        last_inputs = excitatory;
+        report.iterations = iter;
+        report.final_delta = delta;
+        if delta < epsilon {
+            report.converged = true;
+            break;
+        }
+    }
+    report.active = activation.len();
+
+    let mut emitted: HashSet<u64> = HashSet::new();
+    let seed_order = seeds.iter().copied().filter(|(n, _)| emitted.insert(*n));
+    for (node, strength) in seed_order {
+        let level = activation.get(&node).copied().unwrap_or(0.0);
+        sink(WaveEvent::Seed { node, strength, level });
+    }
+    let mut settled: Vec<(u64, f32, u32)> = activation
+        .iter()
+        .filter(|(n, _)| !external.contains_key(n))
+        .filter_map(|(n, v)| first_seen.get(n).map(|it| (*n, *v, *it)))
+        .collect();
+    settled.sort_by(|a, b| {
+        a.2.cmp(&b.2).then(b.1.partial_cmp(&a.1).unwrap_or(std::cmp::Ordering::Equal))
+    });
+    for (node, level, iter) in settled {
+        // Predecessor = strongest excitatory sender in the final iteration.
+        let via = last_inputs.get(&node).and_then(|inputs| {
+            let (&sender, &incoming) = inputs
+                .iter()
+                .max_by(|a, b| a.1.partial_cmp(b.1).unwrap_or(std::cmp::Ordering::Equal))?;
+            let weight = adjacency
+                .get(&sender)
+                .and_then(|out| out.iter().find(|(r, _, _)| *r == node))
+                .map_or(0.0, |(_, w, _)| *w);
+            Some((sender, weight, incoming))
+        });
+        let depth = iter.min(u8::MAX as u32) as u8;
+        sink(WaveEvent::Fired { node, via, level, depth });
+    }
+    report
+}
+
+impl ActivationDynamics {
+    fn accumulates(&self) -> bool {
+        self.mode == "accumulate"
+    }
+}
+
 #[derive(Debug)]
 struct AsyncShared {
     shard_count: usize,
     adjacency: HashMap<u64, Vec<AsyncSynapse>>,
     threshold: HashMap<u64, f32>,
     activation: HashMap<u64, f32>,
+    max_spread_depth: u8,
+    default_threshold: f32,
+    dynamics: ActivationDynamics,
+    activation_tick: u64,
+    last_wave_tick: Option<u64>,
+    /// Async waves started so far; stamps async firings for concept support.
+    wave_seq: u64,
+    waves: HashMap<u64, WaveInputs>,
+    trace_enabled: bool,
+    trace: HashMap<u64, ActivationTrace>,
     ingress_paused: bool,
     global_queue_len: u64,
     per_shard_queue_len: Vec<u64>,
@@ -128,6 +766,8 @@ enum ShardCommand {
         node_id: u64,
         strength: f32,
         origin_tick: u64,
+        depth: u8,
+        #[allow(dead_code)]
         source_shard: usize,
     },
     UpdateEdge {
@@ -155,1096 +795,4602 @@ struct DeltaEntry {
     receiver_id: u64,
     weight: f32,
     timestamp: u32,
+    kind: SynapseKind,
+    tombstone: bool,
 }
 
-#[pyclass]
-struct RagpEngine {
-    storage_dir: PathBuf,
-    base_path: PathBuf,
-    delta_path: PathBuf,
-    node_index: HashMap<u64, NodeMeta>,
-    delta_index: HashMap<u64, HashMap<u64, (f32, u32)>>,
-    activation: HashMap<u64, f32>,
-    temporal_window: VecDeque<(u64, f32, u32)>,
-    tick: u32,
-
-    // Hybrid cache: pinned + LRU
-    base_cache: LruCache<u64, Vec<Synapse>>,
-    pinned_cache: HashMap<u64, Vec<Synapse>>,
-    pinned_set: HashSet<u64>,
-    access_count: HashMap<u64, u32>,
-    access_since_recompute: u32,
-
-    // Cache policy config
-    cache_policy: String,
-    cache_ram_fraction: f32,
-    cache_ram_min_mb: u64,
-    cache_ram_max_mb: u64,
-    cache_pin_fraction: f32,
-
-    // Computed cache budgets/metrics
-    cache_budget_bytes: u64,
-    pinned_budget_bytes: u64,
-    lru_budget_bytes: u64,
-    cache_bytes_est: u64,
-    pinned_bytes_est: u64,
-    lru_bytes_est: u64,
-    registry_version: u32,
-    loaded_registry_version: u32,
-    async_state: AsyncRuntimeState,
-    async_runtime: Option<AsyncActorRuntime>,
+/// Newest delta state of one edge plus its write history since the last
+/// consolidation, which `fold_delta` adds onto the base synapse.
+#[derive(Clone, Copy, Debug)]
+struct DeltaEdge {
+    weight: f32,
+    timestamp: u32,
+    kind: SynapseKind,
+    first_ts: u32,
+    updates: u32,
+    /// The newest write evicted the edge.
+    removed: bool,
+    /// The edge was evicted and re-formed since the last consolidation, so any
+    /// base synapse's history no longer applies.
+    reset: bool,
 }
 
-impl RagpEngine {
-    fn crc32(data: &[u8]) -> u32 {
-        crc32fast::hash(data)
+impl DeltaEdge {
+    /// Folds one delta write into `edges`; the newest timestamp wins the weight.
+    /// A tombstone clears the edge's history and a write after it starts afresh.
+    fn record(edges: &mut HashMap<u64, DeltaEdge>, receiver: u64, entry: &DeltaEntry) {
+        let fresh = DeltaEdge {
+            weight: entry.weight,
+            timestamp: entry.timestamp,
+            kind: entry.kind,
+            first_ts: entry.timestamp,
+            updates: u32::from(!entry.tombstone),
+            removed: entry.tombstone,
+            reset: false,
+        };
+        let Some(edge) = edges.get_mut(&receiver) else {
+            edges.insert(receiver, fresh);
+            return;
+        };
+        if entry.tombstone || edge.removed {
+            if entry.timestamp >= edge.timestamp {
+                *edge = DeltaEdge {
+                    reset: !entry.tombstone,
+                    ..fresh
+                };
+            }
+            return;
+        }
+        edge.updates = edge.updates.saturating_add(1);
+        edge.first_ts = edge.first_ts.min(entry.timestamp);
+        if entry.timestamp >= edge.timestamp {
+            edge.weight = entry.weight;
+            edge.timestamp = entry.timestamp;
+            edge.kind = entry.kind;
+        }
     }
+}
 
-    fn env_f32(key: &str, default: f32) -> f32 {
-        env::var(key)
-            .ok()
-            .and_then(|v| v.parse::<f32>().ok())
-            .unwrap_or(default)
-    }
+#[derive(Clone, Debug, PartialEq)]
+enum PruneTerm {
+    /// Drop below `ratio * mean(|weight|)` of the sender; `None` uses `prune_ratio`.
+    RelativeMean(Option<f32>),
+    /// Drop below a fixed `|weight|` floor.
+    Absolute(f32),
+    /// Keep only the k strongest edges of the sender.
+    TopK(usize),
+    /// Drop edges not updated for more than N ticks (edges with unknown age are kept).
+    Age(u32),
+}
 
-    fn env_u64(key: &str, default: u64) -> u64 {
-        env::var(key)
-            .ok()
-            .and_then(|v| v.parse::<u64>().ok())
-            .unwrap_or(default)
-    }
+/// Parsed pruning spec. Terms joined by `+` prune an edge when any term says so,
+/// terms joined by `&` only when all of them do. `none` disables pruning.
+#[derive(Clone, Debug, PartialEq)]
+struct PruneStrategy {
+    spec: String,
+    terms: Vec<PruneTerm>,
+    require_all: bool,
+}
 
-    fn env_u32(key: &str, default: u32) -> u32 {
-        env::var(key)
-            .ok()
-            .and_then(|v| v.parse::<u32>().ok())
-            .unwrap_or(default)
-    }
+impl PruneStrategy {
+    fn parse(spec: &str) -> Result<Self, String> {
+        let spec = spec.trim().to_ascii_lowercase();
+        if spec.is_empty() {
+            return Err("prune strategy must not be empty".to_string());
+        }
+        if spec == "none" {
+            return Ok(PruneStrategy {
+                spec,
+                terms: Vec::new(),
+                require_all: false,
+            });
+        }
+        if spec.contains('+') && spec.contains('&') {
+            return Err(format!("prune strategy '{}' mixes '+' and '&'", spec));
+        }
+        let require_all = spec.contains('&');
+        let sep = if require_all { '&' } else { '+' };
+
+        let mut terms = Vec::new();
+        for raw in spec.split(sep) {
+            let raw = raw.trim();
+            let (name, arg) = match raw.split_once(':') {
+                Some((n, a)) => (n.trim(), Some(a.trim())),
+                None => (raw, None),
+            };
+            let bad = || format!("invalid prune term '{}'", raw);
+            let term = match (name, arg) {
+                ("relative_mean", None) => PruneTerm::RelativeMean(None),
+                ("relative_mean", Some(a)) => {
+                    let v = a.parse::<f32>().map_err(|_| bad())?;
+                    if !(0.0..=1.0).contains(&v) {
+                        return Err(format!("relative_mean ratio must be in [0,1], got {}", v));
+                    }
+                    PruneTerm::RelativeMean(Some(v))
+                }
+                ("absolute", Some(a)) => {
+                    let v = a.parse::<f32>().map_err(|_| bad())?;
+                    if !(0.0..=1.0).contains(&v) {
+                        return Err(format!("absolute floor must be in [0,1], got {}", v));
+                    }
+                    PruneTerm::Absolute(v)
+                }
+                ("top_k", Some(a)) => {
+                    let v = a.parse::<usize>().map_err(|_| bad())?;
+                    if v == 0 {
+                        return Err("top_k must be >= 1".to_string());
+                    }
+                    PruneTerm::TopK(v)
+                }
+                ("age", Some(a)) => PruneTerm::Age(a.parse::<u32>().map_err(|_| bad())?),
+                _ => return Err(bad()),
+            };
+            terms.push(term);
+        }
 
-    fn default_shard_count() -> usize {
-        let cpus = std::thread::available_parallelism()
-            .map(|n| n.get())
-            .unwrap_or(4);
-        let half = (cpus / 2).max(2);
-        half
+        Ok(PruneStrategy {
+            spec,
+            terms,
+            require_all,
+        })
     }
 
-    fn default_async_state() -> AsyncRuntimeState {
-        AsyncRuntimeState {
-            enabled: false,
-            ingress_paused: false,
-            shard_count: Self::default_shard_count(),
-            global_queue_len: 0,
-            dropped_total: 0,
-            coalesced_total: 0,
-            hop_total: 0,
-            processed_total: 0,
-            processed_per_sec: 0.0,
-            last_rate_ts_ms: 0,
-            last_rate_processed_total: 0,
-            guard_mode: "normal".to_string(),
-            per_shard_queue_len: vec![0; Self::default_shard_count()],
-            per_shard_processed: vec![0; Self::default_shard_count()],
-            policy: AsyncPolicy {
-                ram_warn_mb: DEFAULT_ASYNC_RAM_WARN_MB,
-                ram_critical_mb: DEFAULT_ASYNC_RAM_CRITICAL_MB,
-                coalesce_window_ms: DEFAULT_ASYNC_COALESCE_WINDOW_MS,
-                write_throttle_per_sec: DEFAULT_ASYNC_WRITE_THROTTLE_PER_SEC,
-            },
+    /// `apply` run separately over each synapse kind, so small cost or transition
+    /// weights are not judged against the sender's strong associations.
+    fn apply_by_kind(&self, synapses: &mut Vec<Synapse>, now: u32, default_ratio: f32) -> usize {
+        let mut dropped = 0;
+        let mut kept: Vec<Synapse> = Vec::with_capacity(synapses.len());
+        for kind in SynapseKind::ALL {
+            let mut group: Vec<Synapse> =
+                synapses.iter().filter(|s| s.kind == kind).cloned().collect();
+            dropped += self.apply(&mut group, now, default_ratio);
+            kept.append(&mut group);
         }
+        *synapses = kept;
+        dropped
     }
 
-    fn owner_shard(&self, sender: u64) -> usize {
-        if self.async_state.shard_count == 0 {
+    /// Removes pruned edges in place and returns how many were dropped.
+    /// Age terms read each synapse's `last_updated`.
+    fn apply(&self, synapses: &mut Vec<Synapse>, now: u32, default_ratio: f32) -> usize {
+        if self.terms.is_empty() || synapses.is_empty() {
             return 0;
         }
-        (sender as usize) % self.async_state.shard_count
-    }
 
-    fn refresh_async_guard_mode(&mut self) {
-        let mut sys = System::new();
-        sys.refresh_memory();
-        let avail_bytes = Self::normalize_available_bytes(sys.available_memory());
-        let avail_mb = avail_bytes / (1024 * 1024);
-
-        self.async_state.guard_mode = if avail_mb <= self.async_state.policy.ram_critical_mb {
-            "critical".to_string()
-        } else if avail_mb <= self.async_state.policy.ram_warn_mb {
-            "warn".to_string()
-        } else {
-            "normal".to_string()
-        };
-    }
+        // Inhibitory edges are judged by magnitude, same as excitatory ones.
+        let n = synapses.len();
+        let mean = synapses.iter().map(|s| s.weight.abs()).sum::<f32>() / n as f32;
+        let mut rank = vec![0_usize; n];
+        let mut order: Vec<usize> = (0..n).collect();
+        order.sort_by(|a, b| {
+            synapses[*b]
+                .weight
+                .abs()
+                .partial_cmp(&synapses[*a].weight.abs())
+                .unwrap_or(std::cmp::Ordering::Equal)
+        });
+        for (r, idx) in order.into_iter().enumerate() {
+            rank[idx] = r;
+        }
 
-    fn now_ms() -> u64 {
-        use std::time::{SystemTime, UNIX_EPOCH};
-        match SystemTime::now().duration_since(UNIX_EPOCH) {
-            Ok(d) => d.as_millis() as u64,
-            Err(_) => 0,
-        }
+        let drop_flags: Vec<bool> = synapses
+            .iter()
+            .enumerate()
+            .map(|(idx, s)| {
+                let mut votes = self.terms.iter().map(|term| match term {
+                    PruneTerm::RelativeMean(ratio) => {
+                        s.weight.abs() < mean * ratio.unwrap_or(default_ratio)
+                    }
+                    PruneTerm::Absolute(floor) => s.weight.abs() < *floor,
+                    PruneTerm::TopK(k) => rank[idx] >= *k,
+                    PruneTerm::Age(max_age) => {
+                        s.has_history() && now.saturating_sub(s.last_updated) > *max_age
+                    }
+                });
+                if self.require_all {
+                    votes.all(|v| v)
+                } else {
+                    votes.any(|v| v)
+                }
+            })
+            .collect();
+
+        let mut idx = 0;
+        synapses.retain(|_| {
+            let keep = !drop_flags[idx];
+            idx += 1;
+            keep
+        });
+        n - synapses.len()
     }
+}
 
-    fn refresh_async_processed_rate(&mut self) {
-        let now = Self::now_ms();
-        if self.async_state.last_rate_ts_ms == 0 {
-            self.async_state.last_rate_ts_ms = now;
-            self.async_state.last_rate_processed_total = self.async_state.processed_total;
-            self.async_state.processed_per_sec = 0.0;
-            return;
+/// Sender-range overrides: `"1-99=top_k:20;100-199=absolute:0.05"`.
+fn parse_prune_ranges(spec: &str) -> Result<Vec<(u64, u64, PruneStrategy)>, String> {
+    let mut out = Vec::new();
+    for part in spec.split(';') {
+        let part = part.trim();
+        if part.is_empty() {
+            continue;
         }
-
-        let dt_ms = now.saturating_sub(self.async_state.last_rate_ts_ms);
-        if dt_ms < 200 {
-            return;
+        let (range, strategy) = part
+            .split_once('=')
+            .ok_or_else(|| format!("invalid prune range '{}': expected start-end=strategy", part))?;
+        let (start, end) = range
+            .split_once('-')
+            .ok_or_else(|| format!("invalid prune range '{}': expected start-end", range))?;
+        let start = start
+            .trim()
+            .parse::<u64>()
+            .map_err(|_| format!("invalid prune range start '{}'", start))?;
+        let end = end
+            .trim()
+            .parse::<u64>()
+            .map_err(|_| format!("invalid prune range end '{}'", end))?;
+        if end < start {
+            return Err(format!("invalid prune range {}-{}: end < start", start, end));
         }
-        let dp = self
-            .async_state
-            .processed_total
-            .saturating_sub(self.async_state.last_rate_processed_total);
-        self.async_state.processed_per_sec = (dp as f64) / (dt_ms as f64 / 1000.0);
-        self.async_state.last_rate_ts_ms = now;
-        self.async_state.last_rate_processed_total = self.async_state.processed_total;
+        out.push((start, end, PruneStrategy::parse(strategy)?));
     }
+    Ok(out)
+}
 
-    fn build_async_snapshot(&mut self) -> (HashMap<u64, Vec<AsyncSynapse>>, HashMap<u64, f32>) {
-        let mut senders: Vec<u64> = self.node_index.keys().copied().collect();
-        senders.sort_unstable();
-
-        let mut adjacency: HashMap<u64, Vec<AsyncSynapse>> = HashMap::new();
-        for sender in senders {
-            let conns = self.get_connections_internal(sender);
-            let syns: Vec<AsyncSynapse> = conns
-                .into_iter()
-                .map(|(receiver_id, weight)| AsyncSynapse { receiver_id, weight })
-                .collect();
-            adjacency.insert(sender, syns);
-        }
+/// Components behind one action's `compute_cd` score.
+#[derive(Clone, Debug)]
+struct CdBreakdown {
+    action: u64,
+    /// Stimulus->action weight after drive modulation.
+    value: f32,
+    raw_value: f32,
+    /// Level of the most urgent drive linked to the action (1.0 when none).
+    drive_gain: f32,
+    drive: Option<String>,
+    learned_value: f32,
+    cost: f64,
+    cost_default: bool,
+    cost_edges: usize,
+    opportunity: f64,
+    opportunity_default: bool,
+    /// (context node, context->action weight) pairs averaged into `opportunity`.
+    context_edges: Vec<(u64, f32)>,
+    cd: f64,
+    /// Why the action is not a candidate (`None` when it is ranked).
+    excluded: Option<String>,
+}
 
-        let mut thresholds: HashMap<u64, f32> = HashMap::new();
-        for (node, meta) in &self.node_index {
-            thresholds.insert(*node, meta.threshold);
+/// Typed connection lists `score_cd` reads for one (stimulus, context) pair: the
+/// stimulus, each of its receivers (cost), the context nodes and the drive nodes.
+/// `fetch` decides whether the reads touch the cache (`get_connections_internal`)
+/// or only peek at it (`peek_typed_connections`).
+fn gather_cd_connections<F>(
+    stimulus: u64,
+    context: &[u64],
+    drive_nodes: &[u64],
+    mut fetch: F,
+) -> HashMap<u64, TypedConnections>
+where
+    F: FnMut(u64) -> TypedConnections,
+{
+    let mut conns: HashMap<u64, TypedConnections> = HashMap::new();
+    let stimulus_edges = fetch(stimulus);
+    let receivers: Vec<u64> = stimulus_edges.iter().map(|(r, _, _)| *r).collect();
+    conns.insert(stimulus, stimulus_edges);
+    let others = context.iter().chain(drive_nodes.iter()).copied();
+    for node in receivers.into_iter().chain(others) {
+        if let std::collections::hash_map::Entry::Vacant(slot) = conns.entry(node) {
+            slot.insert(fetch(node));
         }
-        (adjacency, thresholds)
     }
+    conns
+}
 
-    fn sync_async_state_from_shared(&mut self) {
-        let Some(ar) = self.async_runtime.as_ref() else {
-            return;
-        };
-        let snapshot = ar.rt.block_on(async {
-            let s = ar.shared.lock().await;
-            (
-                s.ingress_paused,
-                s.global_queue_len,
-                s.processed_total,
-                s.processed_per_sec,
-                s.dropped_total,
-                s.coalesced_total,
-                s.hop_total,
-                s.guard_mode.clone(),
-                s.per_shard_queue_len.clone(),
-                s.per_shard_processed.clone(),
-            )
-        });
+/// One partial or finished action sequence in `plan`'s beam.
+#[derive(Clone, Debug)]
+struct PlanBranch {
+    actions: Vec<u64>,
+    /// Stimulus followed by the predicted state after each action.
+    states: Vec<u64>,
+    /// Product of the transition weights taken so far.
+    probability: f32,
+    /// Sum over steps of gamma^t * probability * clamp(Cd, -1, 1).
+    expected_value: f64,
+}
 
-        self.async_state.ingress_paused = snapshot.0;
-        self.async_state.global_queue_len = snapshot.1;
-        self.async_state.processed_total = snapshot.2;
-        self.async_state.processed_per_sec = snapshot.3;
-        self.async_state.dropped_total = snapshot.4;
-        self.async_state.coalesced_total = snapshot.5;
-        self.async_state.hop_total = snapshot.6;
-        self.async_state.guard_mode = snapshot.7;
-        self.async_state.per_shard_queue_len = snapshot.8;
-        self.async_state.per_shard_processed = snapshot.9;
-    }
+/// TD(lambda) bookkeeping. A step's state is a feature set of nodes whose values
+/// are averaged; its update is applied one step late, once the next state is known.
+#[derive(Clone, Debug, Default)]
+struct TdState {
+    traces: HashMap<u64, f32>,
+    /// Features and reward of the step awaiting its TD update.
+    prev: Option<(Vec<u64>, f32)>,
+    /// Nodes seeded or selected since the last `apply_reward`.
+    pending: HashSet<u64>,
+    last_rpe: Option<f32>,
+}
 
-    fn env_policy(key: &str, default: &str) -> String {
-        let policy = env::var(key).unwrap_or_else(|_| default.to_string());
-        let normalized = policy.trim().to_ascii_lowercase();
-        if normalized == "pinned_lru" || normalized == "lru" {
-            normalized
-        } else {
-            default.to_string()
+/// Homeostatic drive bound to a sensor node; `level` is the urgency in [0, 1].
+#[derive(Clone, Debug, PartialEq)]
+struct DriveState {
+    node: u64,
+    level: f32,
+}
+
+/// Learned node standing for a set of nodes that kept firing together.
+#[derive(Clone, Debug)]
+struct Concept {
+    members: Vec<u64>,
+    support: u32,
+    formed_at: u32,
+}
+
+/// When a node last fired: its window tick (sync path) and the async wave
+/// counter. The two clocks are compared separately, never against each other.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+struct FiringStamp {
+    tick: u32,
+    wave: u64,
+}
+
+impl FiringStamp {
+    fn max(self, other: FiringStamp) -> FiringStamp {
+        FiringStamp {
+            tick: self.tick.max(other.tick),
+            wave: self.wave.max(other.wave),
         }
     }
 
-    fn clamp_f32(v: f32, lo: f32, hi: f32) -> f32 {
-        v.max(lo).min(hi)
+    fn is_newer_than(self, counted: FiringStamp) -> bool {
+        self.tick > counted.tick || self.wave > counted.wave
     }
+}
 
-    fn normalize_available_bytes(raw: u64) -> u64 {
-        // Sysinfo versions differ (bytes vs KiB). Normalize to bytes heuristically.
-        if raw < (1_u64 << 31) {
-            raw.saturating_mul(1024)
-        } else {
-            raw
+/// All `k`-element subsets of `items`, each in `items` order.
+fn combinations(items: &[u64], k: usize) -> Vec<Vec<u64>> {
+    let mut out = Vec::new();
+    if k == 0 || k > items.len() {
+        return out;
+    }
+    let n = items.len();
+    let mut idx: Vec<usize> = (0..k).collect();
+    loop {
+        out.push(idx.iter().map(|&i| items[i]).collect());
+        let Some(pos) = (0..k).rev().find(|&i| idx[i] != n - k + i) else {
+            return out;
+        };
+        idx[pos] += 1;
+        for i in pos + 1..k {
+            idx[i] = idx[i - 1] + 1;
         }
     }
+}
 
-    fn node_cache_bytes_from_len(len: usize) -> u64 {
-        (len as u64).saturating_mul(SYNAPSE_SIZE).saturating_add(64)
-    }
+/// One action in the `select_action` competition.
+#[derive(Clone, Debug)]
+struct ActionCandidate {
+    action: u64,
+    /// Stimulus whose Cd scored this action best.
+    stimulus: u64,
+    cd: f64,
+    /// `tanh(Cd)`; the candidate's external drive.
+    drive: f32,
+    /// Activity after lateral inhibition settles.
+    activity: f32,
+}
 
-    fn chunk_start_for_sender(sender: u64) -> u64 {
-        if sender == 0 {
-            return 1;
+/// Edge capacity of one sender during a formation pass, so the merged count and
+/// eviction order are computed once per sender instead of once per new edge.
+#[derive(Clone, Debug, Default)]
+struct EdgeRoom {
+    /// Edges that can still be formed before the cap.
+    free: u32,
+    /// Association receivers in reverse eviction order (next victim last); filled
+    /// on the first eviction.
+    victims: Option<Vec<u64>>,
+    /// Receivers given a slot in this pass; never evicted by the same pass.
+    formed: HashSet<u64>,
+}
+
+#[derive(Clone, Debug, Default)]
+struct SettleReport {
+    iterations: u32,
+    converged: bool,
+    final_delta: f32,
+    active: usize,
+}
+
+#[derive(Clone, Debug, Default)]
+struct ConsolidationReport {
+    merged: u32,
+    pruned: u32,
+    decayed: u32,
+    kept: u64,
+    tick: u32,
+    strategy: String,
+    ranges: String,
+    // spec -> (nodes evaluated, edges pruned)
+    per_strategy: HashMap<String, (u32, u32)>,
+}
+
+#[derive(Clone, Debug)]
+enum ConfigValue {
+    Int(i64),
+    Float(f64),
+    Str(String),
+    Bool(bool),
+}
+
+impl ConfigValue {
+    fn parse_raw(raw: &str) -> Self {
+        let trimmed = raw.trim();
+        if let Ok(v) = trimmed.to_ascii_lowercase().parse::<bool>() {
+            ConfigValue::Bool(v)
+        } else if let Ok(v) = trimmed.parse::<i64>() {
+            ConfigValue::Int(v)
+        } else if let Ok(v) = trimmed.parse::<f64>() {
+            ConfigValue::Float(v)
+        } else {
+            ConfigValue::Str(trimmed.to_string())
         }
-        ((sender - 1) / CHUNK_SPAN) * CHUNK_SPAN + 1
     }
 
-    fn chunk_end_from_start(start: u64) -> u64 {
-        start.saturating_add(CHUNK_SPAN - 1)
+    fn from_toml(key: &str, value: &toml::Value) -> Result<Self, String> {
+        match value {
+            toml::Value::Integer(v) => Ok(ConfigValue::Int(*v)),
+            toml::Value::Float(v) => Ok(ConfigValue::Float(*v)),
+            toml::Value::String(v) => Ok(ConfigValue::Str(v.clone())),
+            toml::Value::Boolean(v) => Ok(ConfigValue::Bool(*v)),
+            _ => Err(format!("{}: unsupported TOML value type", key)),
+        }
     }
 
-    fn chunk_file_name(start: u64) -> String {
-        let end = Self::chunk_end_from_start(start);
-        format!("base_{:06}_{:06}.bin", start, end)
+    fn from_py(key: &str, value: &Bound<'_, PyAny>) -> Result<Self, String> {
+        if let Ok(v) = value.extract::<bool>() {
+            return Ok(ConfigValue::Bool(v));
+        }
+        if let Ok(v) = value.extract::<i64>() {
+            return Ok(ConfigValue::Int(v));
+        }
+        if let Ok(v) = value.extract::<f64>() {
+            return Ok(ConfigValue::Float(v));
+        }
+        if let Ok(v) = value.extract::<String>() {
+            return Ok(ConfigValue::Str(v));
+        }
+        Err(format!("{}: expected int, float, str or bool", key))
     }
 
-    fn chunk_file_path(&self, start: u64) -> PathBuf {
-        self.storage_dir.join(Self::chunk_file_name(start))
+    fn as_f32(&self, key: &str) -> Result<f32, String> {
+        match self {
+            ConfigValue::Int(v) => Ok(*v as f32),
+            ConfigValue::Float(v) if v.is_finite() && (*v as f32).is_infinite() => {
+                Err(format!("{} is out of range, got {:e}", key, v))
+            }
+            ConfigValue::Float(v) => Ok(*v as f32),
+            _ => Err(format!("{}: expected a number, got {:?}", key, self)),
+        }
     }
 
-    fn encode_chunk_offset(chunk_start: u64, local_offset: u32) -> u64 {
-        OFFSET_CHUNK_FLAG | (chunk_start << 32) | u64::from(local_offset)
+    fn as_u64(&self, key: &str) -> Result<u64, String> {
+        match self {
+            ConfigValue::Int(v) if *v >= 0 => Ok(*v as u64),
+            _ => Err(format!("{}: expected a non-negative integer, got {:?}", key, self)),
+        }
     }
 
-    fn is_chunk_offset(encoded: u64) -> bool {
-        (encoded & OFFSET_CHUNK_FLAG) != 0
+    /// Rejects (rather than saturates) values the field cannot hold, so the error
+    /// names the value the caller passed.
+    fn as_u32(&self, key: &str) -> Result<u32, String> {
+        let v = self.as_u64(key)?;
+        u32::try_from(v).map_err(|_| format!("{} must be <= {}, got {}", key, u32::MAX, v))
     }
 
-    fn decode_chunk_offset(encoded: u64) -> (u64, u64) {
-        let chunk_start = (encoded & !OFFSET_CHUNK_FLAG) >> 32;
-        let local_offset = encoded & 0xFFFF_FFFF;
-        (chunk_start, local_offset)
+    fn as_u8(&self, key: &str) -> Result<u8, String> {
+        let v = self.as_u64(key)?;
+        u8::try_from(v).map_err(|_| format!("{} must be <= {}, got {}", key, u8::MAX, v))
     }
 
-    fn chunk_file_starts(&self) -> Vec<u64> {
-        let mut out: Vec<u64> = Vec::new();
-        let Ok(entries) = fs::read_dir(&self.storage_dir) else {
-            return out;
-        };
-        for entry in entries.flatten() {
-            let name = entry.file_name().to_string_lossy().to_string();
-            if !name.starts_with("base_") || !name.ends_with(".bin") {
-                continue;
-            }
-            let raw = name.trim_end_matches(".bin");
-            let parts: Vec<&str> = raw.split('_').collect();
-            if parts.len() != 3 {
-                continue;
-            }
-            if let Ok(start) = parts[1].parse::<u64>() {
-                out.push(start);
-            }
+    fn as_bool(&self, key: &str) -> Result<bool, String> {
+        match self {
+            ConfigValue::Bool(v) => Ok(*v),
+            ConfigValue::Int(0) => Ok(false),
+            ConfigValue::Int(1) => Ok(true),
+            ConfigValue::Str(v) => match v.trim().to_ascii_lowercase().as_str() {
+                "on" | "yes" => Ok(true),
+                "off" | "no" => Ok(false),
+                _ => Err(format!("{}: expected a bool, got {:?}", key, self)),
+            },
+            _ => Err(format!("{}: expected a bool, got {:?}", key, self)),
         }
-        out.sort_unstable();
-        out
     }
 
-    fn clear_chunk_files(&self) {
-        let starts = self.chunk_file_starts();
-        for start in starts {
-            let _ = fs::remove_file(self.chunk_file_path(start));
+    fn as_string(&self, key: &str) -> Result<String, String> {
+        match self {
+            ConfigValue::Str(v) => Ok(v.trim().to_ascii_lowercase()),
+            _ => Err(format!("{}: expected a string, got {:?}", key, self)),
         }
     }
+}
 
-    fn has_chunk_files(&self) -> bool {
-        !self.chunk_file_starts().is_empty()
-    }
+/// Conversion between one `EngineConfig` field and its config/TOML value.
+trait ConfigField: Sized {
+    fn from_config(value: &ConfigValue, key: &str) -> Result<Self, String>;
+    fn to_toml(&self) -> toml::Value;
+}
 
-    fn refresh_cache_budget(&mut self) {
-        let mut sys = System::new();
-        sys.refresh_memory();
+impl ConfigField for f32 {
+    fn from_config(value: &ConfigValue, key: &str) -> Result<Self, String> {
+        value.as_f32(key)
+    }
+    fn to_toml(&self) -> toml::Value {
+        EngineConfig::toml_f32(*self)
+    }
+}
 
-        let avail_raw = sys.available_memory();
-        let avail_bytes = Self::normalize_available_bytes(avail_raw);
+impl ConfigField for u8 {
+    fn from_config(value: &ConfigValue, key: &str) -> Result<Self, String> {
+        value.as_u8(key)
+    }
+    fn to_toml(&self) -> toml::Value {
+        toml::Value::Integer(*self as i64)
+    }
+}
 
-        let fraction = Self::clamp_f32(self.cache_ram_fraction, 0.01, 0.90);
-        let min_bytes = self.cache_ram_min_mb.saturating_mul(1024 * 1024);
-        let max_bytes = self.cache_ram_max_mb.saturating_mul(1024 * 1024).max(min_bytes);
+impl ConfigField for u32 {
+    fn from_config(value: &ConfigValue, key: &str) -> Result<Self, String> {
+        value.as_u32(key)
+    }
+    fn to_toml(&self) -> toml::Value {
+        toml::Value::Integer(*self as i64)
+    }
+}
 
-        let mut target = ((avail_bytes as f64) * (fraction as f64)) as u64;
-        if target < min_bytes {
-            target = min_bytes;
-        }
-        if target > max_bytes {
-            target = max_bytes;
-        }
+impl ConfigField for u64 {
+    fn from_config(value: &ConfigValue, key: &str) -> Result<Self, String> {
+        value.as_u64(key)
+    }
+    fn to_toml(&self) -> toml::Value {
+        toml::Value::Integer(*self as i64)
+    }
+}
 
-        self.cache_budget_bytes = target;
-        if self.cache_policy == "pinned_lru" {
-            let pin_fraction = Self::clamp_f32(self.cache_pin_fraction, 0.05, 0.90);
-            self.pinned_budget_bytes = ((target as f64) * (pin_fraction as f64)) as u64;
-            self.lru_budget_bytes = target.saturating_sub(self.pinned_budget_bytes);
-        } else {
-            self.pinned_budget_bytes = 0;
-            self.lru_budget_bytes = target;
-        }
+impl ConfigField for usize {
+    fn from_config(value: &ConfigValue, key: &str) -> Result<Self, String> {
+        value.as_u64(key).map(|v| v as usize)
+    }
+    fn to_toml(&self) -> toml::Value {
+        toml::Value::Integer(*self as i64)
+    }
+}
 
-        self.enforce_cache_budget();
+impl ConfigField for bool {
+    fn from_config(value: &ConfigValue, key: &str) -> Result<Self, String> {
+        value.as_bool(key)
+    }
+    fn to_toml(&self) -> toml::Value {
+        toml::Value::Boolean(*self)
     }
+}
 
-    fn recount_cache_bytes(&mut self) {
-        self.pinned_bytes_est = self
-            .pinned_cache
-            .values()
-            .map(|v| Self::node_cache_bytes_from_len(v.len()))
-            .sum();
-        self.lru_bytes_est = self
-            .base_cache
-            .iter()
-            .map(|(_, v)| Self::node_cache_bytes_from_len(v.len()))
-            .sum();
-        self.cache_bytes_est = self.pinned_bytes_est.saturating_add(self.lru_bytes_est);
+impl ConfigField for String {
+    fn from_config(value: &ConfigValue, key: &str) -> Result<Self, String> {
+        value.as_string(key)
     }
-    fn pinned_score_from_synapses(&self, node_id: u64, synapses: &[Synapse], max_access: f32) -> f32 {
-        let max_weight = synapses
-            .iter()
-            .fold(0.0_f32, |acc, s| if s.weight > acc { s.weight } else { acc });
-        let access = self.access_count.get(&node_id).copied().unwrap_or(0) as f32;
-        let access_norm = if max_access <= 0.0 { 0.0 } else { access / max_access };
-        0.6 * max_weight + 0.4 * access_norm
+    fn to_toml(&self) -> toml::Value {
+        toml::Value::String(self.clone())
     }
+}
 
-    fn lowest_scored_pinned_cached(&self) -> Option<u64> {
-        let max_access = self.access_count.values().copied().max().unwrap_or(1) as f32;
-        let mut worst: Option<(u64, f32)> = None;
-        for (node_id, synapses) in &self.pinned_cache {
-            let score = self.pinned_score_from_synapses(*node_id, synapses, max_access);
-            match worst {
-                Some((_, wscore)) if score >= wscore => {}
-                _ => worst = Some((*node_id, score)),
+/// Declares every config key once: the readable field, its default, `KEYS`,
+/// `apply` (set from a `ConfigValue`) and `get` (read back as TOML).
+macro_rules! engine_config {
+    ($($key:ident: $ty:ty = $default:expr,)*) => {
+        /// Tunable engine parameters. Resolved from defaults, `ragp_config.toml` in the
+        /// storage dir and `RAGP_<KEY>` env vars, or passed explicitly to `RagpEngine`.
+        #[pyclass]
+        #[derive(Clone, Debug)]
+        struct EngineConfig {
+            $(
+                #[pyo3(get)]
+                $key: $ty,
+            )*
+        }
+
+        impl Default for EngineConfig {
+            fn default() -> Self {
+                EngineConfig {
+                    $($key: $default,)*
+                }
             }
         }
-        worst.map(|(id, _)| id)
-    }
 
-    fn enforce_cache_budget(&mut self) {
-        self.recount_cache_bytes();
+        impl EngineConfig {
+            const KEYS: &'static [&'static str] = &[$(stringify!($key)),*];
 
-        while self.lru_bytes_est > self.lru_budget_bytes {
-            if self.base_cache.pop_lru().is_none() {
-                break;
+            fn apply(&mut self, key: &str, value: &ConfigValue) -> Result<(), String> {
+                match key {
+                    $(stringify!($key) => self.$key = ConfigField::from_config(value, key)?,)*
+                    _ => return Err(format!("unknown config key: {}", key)),
+                }
+                Ok(())
             }
-            self.recount_cache_bytes();
-        }
 
-        if self.cache_policy == "pinned_lru" {
-            while self.pinned_bytes_est > self.pinned_budget_bytes {
-                let Some(victim) = self.lowest_scored_pinned_cached() else {
-                    break;
-                };
-                self.pinned_cache.remove(&victim);
-                self.pinned_set.remove(&victim);
-                self.recount_cache_bytes();
+            fn get(&self, key: &str) -> Option<toml::Value> {
+                match key {
+                    $(stringify!($key) => Some(self.$key.to_toml()),)*
+                    _ => None,
+                }
             }
         }
+    };
+}
 
-        while self.cache_bytes_est > self.cache_budget_bytes {
-            if self.base_cache.pop_lru().is_some() {
-                self.recount_cache_bytes();
-                continue;
-            }
-            let Some(victim) = self.lowest_scored_pinned_cached() else {
-                break;
-            };
-            self.pinned_cache.remove(&victim);
-            self.pinned_set.remove(&victim);
-            self.recount_cache_bytes();
+engine_config! {
+    max_synapses_per_node: u32 = DEFAULT_MAX_SYNAPSES_PER_NODE,
+    saturation_policy: String = DEFAULT_SATURATION_POLICY.to_string(),
+    initial_weight: f32 = DEFAULT_INITIAL_WEIGHT,
+    default_threshold: f32 = DEFAULT_THRESHOLD,
+    prune_ratio: f32 = DEFAULT_PRUNE_RATIO,
+    prune_strategy: String = DEFAULT_PRUNE_STRATEGY.to_string(),
+    prune_ranges: String = String::new(),
+    temporal_window_size: usize = DEFAULT_TEMPORAL_WINDOW_SIZE,
+    max_spread_depth: u8 = DEFAULT_MAX_SPREAD_DEPTH,
+    activation_mode: String = DEFAULT_ACTIVATION_MODE.to_string(),
+    activation_decay: f32 = DEFAULT_ACTIVATION_DECAY,
+    activation_floor: f32 = DEFAULT_ACTIVATION_FLOOR,
+    integration: String = DEFAULT_INTEGRATION.to_string(),
+    spread_dynamics: String = DEFAULT_SPREAD_DYNAMICS.to_string(),
+    settle_epsilon: f32 = DEFAULT_SETTLE_EPSILON,
+    settle_max_iters: u32 = DEFAULT_SETTLE_MAX_ITERS,
+    trace_activation: bool = false,
+    action_masking: bool = true,
+    strict_roles: bool = false,
+    select_inhibition: f32 = DEFAULT_SELECT_INHIBITION,
+    select_go_threshold: f32 = DEFAULT_SELECT_GO_THRESHOLD,
+    select_iters: u32 = DEFAULT_SELECT_ITERS,
+    select_temperature: f32 = 0.0,
+    select_epsilon: f32 = 0.0,
+    td_alpha: f32 = DEFAULT_TD_ALPHA,
+    td_gamma: f32 = DEFAULT_TD_GAMMA,
+    td_lambda: f32 = DEFAULT_TD_LAMBDA,
+    cd_value_source: String = "weight".to_string(),
+    cd_min_cost: f32 = DEFAULT_CD_MIN_COST,
+    weight_decay: f32 = 0.0,
+    plan_horizon: u32 = DEFAULT_PLAN_HORIZON,
+    plan_beam_width: u32 = DEFAULT_PLAN_BEAM_WIDTH,
+    concept_support: u32 = DEFAULT_CONCEPT_SUPPORT,
+    concept_size: usize = DEFAULT_CONCEPT_SIZE,
+    concept_max: u32 = 0,
+    lru_capacity: usize = DEFAULT_LRU_CAPACITY,
+    chunk_span: u64 = DEFAULT_CHUNK_SPAN,
+    cache_policy: String = DEFAULT_CACHE_POLICY.to_string(),
+    cache_ram_fraction: f32 = DEFAULT_CACHE_RAM_FRACTION,
+    cache_ram_min_mb: u64 = DEFAULT_CACHE_RAM_MIN_MB,
+    cache_ram_max_mb: u64 = DEFAULT_CACHE_RAM_MAX_MB,
+    cache_pin_fraction: f32 = DEFAULT_CACHE_PIN_FRACTION,
+    learning_mode: String = DEFAULT_LEARNING_MODE.to_string(),
+    stdp_a_plus: f32 = DEFAULT_STDP_A_PLUS,
+    stdp_a_minus: f32 = DEFAULT_STDP_A_MINUS,
+    stdp_tau_plus: f32 = DEFAULT_STDP_TAU_PLUS,
+    stdp_tau_minus: f32 = DEFAULT_STDP_TAU_MINUS,
+}
+
+impl EngineConfig {
+    fn check_range(key: &str, v: f32, lo: f32, hi: f32) -> Result<(), String> {
+        if !v.is_finite() || v < lo || v > hi {
+            return Err(format!("{} must be in [{}, {}], got {}", key, lo, hi, v));
         }
+        Ok(())
     }
 
-    fn get_cached_or_load_base(&mut self, sender: u64) -> Vec<Synapse> {
-        if self.cache_policy == "pinned_lru" {
-            if let Some(v) = self.pinned_cache.get(&sender) {
-                return v.clone();
-            }
+    fn validate(&self) -> Result<(), String> {
+        if self.max_synapses_per_node == 0 {
+            return Err("max_synapses_per_node must be >= 1".to_string());
         }
-
-        if let Some(v) = self.base_cache.get(&sender) {
-            return v.clone();
+        if !matches!(
+            self.saturation_policy.as_str(),
+            "refuse" | "evict_weakest" | "evict_oldest" | "evict_lru"
+        ) {
+            return Err(format!(
+                "saturation_policy must be 'refuse', 'evict_weakest', 'evict_oldest' or 'evict_lru', got '{}'",
+                self.saturation_policy
+            ));
         }
-
-        let loaded = self.load_from_base(sender);
-        if self.cache_policy == "pinned_lru" && self.pinned_set.contains(&sender) {
-            self.pinned_cache.insert(sender, loaded.clone());
-        } else {
-            self.base_cache.put(sender, loaded.clone());
+        Self::check_range("initial_weight", self.initial_weight, 0.0, 1.0)?;
+        if self.initial_weight <= 0.0 {
+            return Err("initial_weight must be > 0".to_string());
         }
-        self.enforce_cache_budget();
-        loaded
-    }
-
-    fn invalidate_sender_cache(&mut self, sender: u64) {
-        self.pinned_cache.remove(&sender);
-        self.base_cache.pop(&sender);
-        self.enforce_cache_budget();
-    }
-
-    fn record_access(&mut self, sender: u64) {
-        let entry = self.access_count.entry(sender).or_insert(0);
-        *entry = entry.saturating_add(1);
-        self.access_since_recompute = self.access_since_recompute.saturating_add(1);
-        if self.access_since_recompute >= CACHE_RECOMPUTE_ACCESS_INTERVAL {
-            self.access_since_recompute = 0;
-            self.refresh_cache_budget();
-            self.recompute_pinned_set(false);
+        Self::check_range("default_threshold", self.default_threshold, 0.0, 1.0)?;
+        Self::check_range("prune_ratio", self.prune_ratio, 0.0, 1.0)?;
+        PruneStrategy::parse(&self.prune_strategy).map_err(|e| format!("prune_strategy: {}", e))?;
+        parse_prune_ranges(&self.prune_ranges).map_err(|e| format!("prune_ranges: {}", e))?;
+        if self.temporal_window_size == 0 || self.temporal_window_size > 4096 {
+            return Err(format!(
+                "temporal_window_size must be in [1, 4096], got {}",
+                self.temporal_window_size
+            ));
         }
-    }
-
-    fn recompute_pinned_set(&mut self, eager_warm: bool) {
-        if self.cache_policy != "pinned_lru" {
-            self.pinned_set.clear();
-            self.pinned_cache.clear();
-            self.enforce_cache_budget();
-            return;
+        if self.max_spread_depth == 0 || self.max_spread_depth > 64 {
+            return Err(format!(
+                "max_spread_depth must be in [1, 64], got {}",
+                self.max_spread_depth
+            ));
         }
-
-        let max_access = self.access_count.values().copied().max().unwrap_or(1) as f32;
-        let node_ids: Vec<u64> = self.node_index.keys().copied().collect();
-        let mut scored: Vec<(u64, f32, u64)> = Vec::with_capacity(node_ids.len());
-
-        for node_id in node_ids {
-            let synapses = if let Some(v) = self.pinned_cache.get(&node_id) {
-                v.clone()
-            } else if let Some(v) = self.base_cache.get(&node_id) {
-                v.clone()
-            } else {
-                self.load_from_base(node_id)
-            };
-            let score = self.pinned_score_from_synapses(node_id, &synapses, max_access);
-            let est = Self::node_cache_bytes_from_len(synapses.len());
-            scored.push((node_id, score, est));
+        if self.activation_mode != "reset" && self.activation_mode != "accumulate" {
+            return Err(format!(
+                "activation_mode must be 'reset' or 'accumulate', got '{}'",
+                self.activation_mode
+            ));
         }
-
-        scored.sort_by(|a, b| b.1.partial_cmp(&a.1).unwrap_or(std::cmp::Ordering::Equal));
-
-        let mut new_pinned: HashSet<u64> = HashSet::new();
-        let mut used: u64 = 0;
-        for (node_id, _, est) in scored {
-            let next = used.saturating_add(est);
-            if !new_pinned.is_empty() && next > self.pinned_budget_bytes {
-                continue;
-            }
-            if new_pinned.is_empty() && est > self.pinned_budget_bytes {
-                new_pinned.insert(node_id);
-                used = est;
-                continue;
-            }
-            if next <= self.pinned_budget_bytes {
-                new_pinned.insert(node_id);
-                used = next;
-            }
+        Self::check_range("activation_decay", self.activation_decay, 0.0, 1.0)?;
+        Self::check_range("activation_floor", self.activation_floor, 0.0, 1.0)?;
+        Integration::parse(&self.integration)?;
+        if self.spread_dynamics != "bfs" && self.spread_dynamics != "settle" {
+            return Err(format!(
+                "spread_dynamics must be 'bfs' or 'settle', got '{}'",
+                self.spread_dynamics
+            ));
         }
-        self.pinned_set = new_pinned;
-
-        let old_pinned_keys: Vec<u64> = self.pinned_cache.keys().copied().collect();
-        for key in old_pinned_keys {
-            if !self.pinned_set.contains(&key) {
-                if let Some(v) = self.pinned_cache.remove(&key) {
-                    self.base_cache.put(key, v);
-                }
-            }
+        if !self.settle_epsilon.is_finite() || self.settle_epsilon <= 0.0 {
+            return Err(format!("settle_epsilon must be > 0, got {}", self.settle_epsilon));
         }
-
-        let keys_to_promote: Vec<u64> = self.pinned_set.iter().copied().collect();
-        for key in keys_to_promote {
-            if self.pinned_cache.contains_key(&key) {
-                continue;
-            }
-            if let Some(v) = self.base_cache.pop(&key) {
-                self.pinned_cache.insert(key, v);
-                continue;
-            }
-            if eager_warm {
-                let loaded = self.load_from_base(key);
-                self.pinned_cache.insert(key, loaded);
-            }
+        if self.settle_max_iters == 0 || self.settle_max_iters > 10_000 {
+            return Err(format!(
+                "settle_max_iters must be in [1, 10000], got {}",
+                self.settle_max_iters
+            ));
         }
-
-        self.enforce_cache_budget();
-    }
-    fn load_node_index(&mut self) {
-        self.node_index.clear();
-        self.loaded_registry_version = DEFAULT_INNATE_REGISTRY_VERSION;
-        let mut f = match File::open(&self.base_path) {
-            Ok(file) => file,
-            Err(_) => return,
-        };
-
-        let mut header = [0_u8; BASE_HEADER_SIZE as usize];
-        if f.read_exact(&mut header).is_err() {
-            return;
+        Self::check_range("select_inhibition", self.select_inhibition, 0.0, 1.0)?;
+        Self::check_range("select_go_threshold", self.select_go_threshold, 0.0, 1.0)?;
+        if self.select_iters == 0 || self.select_iters > 1000 {
+            return Err(format!("select_iters must be in [1, 1000], got {}", self.select_iters));
         }
-
-        let magic = u32::from_le_bytes(header[0..4].try_into().unwrap());
-        if magic != MAGIC_BASE {
-            return;
+        Self::check_range("select_temperature", self.select_temperature, 0.0, 100.0)?;
+        Self::check_range("select_epsilon", self.select_epsilon, 0.0, 1.0)?;
+        Self::check_range("td_alpha", self.td_alpha, 0.0, 1.0)?;
+        Self::check_range("td_gamma", self.td_gamma, 0.0, 1.0)?;
+        Self::check_range("td_lambda", self.td_lambda, 0.0, 1.0)?;
+        if !matches!(self.cd_value_source.as_str(), "weight" | "td" | "blend") {
+            return Err(format!(
+                "cd_value_source must be 'weight', 'td' or 'blend', got '{}'",
+                self.cd_value_source
+            ));
         }
-        let version = u16::from_le_bytes(header[4..6].try_into().unwrap());
-        if version != VERSION {
-            return;
+        Self::check_range("cd_min_cost", self.cd_min_cost, 0.0, 1.0)?;
+        if self.cd_min_cost <= 0.0 {
+            return Err("cd_min_cost must be > 0".to_string());
         }
-        let reg = u32::from_le_bytes(header[10..14].try_into().unwrap());
-        if reg > 0 {
-            self.loaded_registry_version = reg;
+        if !(0.0..1.0).contains(&self.weight_decay) {
+            return Err(format!("weight_decay must be in [0, 1), got {}", self.weight_decay));
         }
-
-        let node_count = u32::from_le_bytes(header[6..10].try_into().unwrap());
-        for _ in 0..node_count {
-            let mut rec = [0_u8; NODE_INDEX_SIZE as usize];
-            if f.read_exact(&mut rec).is_err() {
-                break;
-            }
-            let node_id = u64::from_le_bytes(rec[0..8].try_into().unwrap());
-            let synapse_count = u32::from_le_bytes(rec[8..12].try_into().unwrap());
-            let synapse_offset = u64::from_le_bytes(rec[12..20].try_into().unwrap());
-            let threshold = f32::from_le_bytes(rec[20..24].try_into().unwrap());
-            let checksum = u32::from_le_bytes(rec[24..28].try_into().unwrap());
-            self.node_index.insert(
-                node_id,
-                NodeMeta {
-                    node_id,
-                    synapse_count,
-                    synapse_offset,
-                    threshold,
-                    checksum,
-                },
-            );
+        if self.plan_horizon == 0 || self.plan_horizon > 16 {
+            return Err(format!("plan_horizon must be in [1, 16], got {}", self.plan_horizon));
         }
-    }
-
-    fn load_delta_index(&mut self) {
-        let mut f = match File::open(&self.delta_path) {
-            Ok(file) => file,
-            Err(_) => return,
-        };
-
-        let mut header = [0_u8; DELTA_HEADER_SIZE as usize];
-        if f.read_exact(&mut header).is_err() {
-            return;
+        if self.plan_beam_width == 0 || self.plan_beam_width > 64 {
+            return Err(format!(
+                "plan_beam_width must be in [1, 64], got {}",
+                self.plan_beam_width
+            ));
         }
-        let magic = u32::from_le_bytes(header[0..4].try_into().unwrap());
-        if magic != MAGIC_DELTA {
-            return;
+        if self.concept_support == 0 {
+            return Err("concept_support must be >= 1".to_string());
         }
-        let version = u16::from_le_bytes(header[4..6].try_into().unwrap());
-        if version != VERSION {
-            return;
+        if !(2..=CONCEPT_CANDIDATE_NODES).contains(&self.concept_size) {
+            return Err(format!(
+                "concept_size must be in [2, {}], got {}",
+                CONCEPT_CANDIDATE_NODES, self.concept_size
+            ));
         }
-        let delta_registry_version = u16::from_le_bytes(header[6..8].try_into().unwrap()) as u32;
-        if delta_registry_version != self.registry_version {
-            return;
+        if self.lru_capacity == 0 {
+            return Err("lru_capacity must be >= 1".to_string());
         }
-
-        let file_size = match f.metadata() {
-            Ok(m) => m.len(),
-            Err(_) => return,
-        };
-        if file_size <= DELTA_HEADER_SIZE {
-            return;
+        if self.chunk_span == 0 || self.chunk_span > 999_999 {
+            return Err(format!("chunk_span must be in [1, 999999], got {}", self.chunk_span));
         }
-
-        let entry_count = (file_size - DELTA_HEADER_SIZE) / DELTA_ENTRY_SIZE;
-        let mut max_ts = self.tick;
-        for _ in 0..entry_count {
-            let mut raw = [0_u8; DELTA_ENTRY_SIZE as usize];
-            if f.read_exact(&mut raw).is_err() {
-                break;
-            }
-
-            let payload = &raw[0..24];
-            let checksum = u32::from_le_bytes(raw[24..28].try_into().unwrap());
-            if Self::crc32(payload) != checksum {
-                continue;
-            }
-
-            let sender = u64::from_le_bytes(raw[0..8].try_into().unwrap());
-            let receiver = u64::from_le_bytes(raw[8..16].try_into().unwrap());
-            let weight = f32::from_le_bytes(raw[16..20].try_into().unwrap());
-            let timestamp = u32::from_le_bytes(raw[20..24].try_into().unwrap());
-
-            if !self.node_index.contains_key(&sender) || !self.node_index.contains_key(&receiver) {
-                continue;
+        if self.cache_policy != "pinned_lru" && self.cache_policy != "lru" {
+            return Err(format!(
+                "cache_policy must be 'pinned_lru' or 'lru', got '{}'",
+                self.cache_policy
+            ));
+        }
+        Self::check_range("cache_ram_fraction", self.cache_ram_fraction, 0.01, 0.90)?;
+        Self::check_range("cache_pin_fraction", self.cache_pin_fraction, 0.05, 0.90)?;
+        if self.cache_ram_min_mb == 0 {
+            return Err("cache_ram_min_mb must be >= 1".to_string());
+        }
+        if self.cache_ram_max_mb < self.cache_ram_min_mb {
+            return Err(format!(
+                "cache_ram_max_mb ({}) must be >= cache_ram_min_mb ({})",
+                self.cache_ram_max_mb, self.cache_ram_min_mb
+            ));
+        }
+        if self.learning_mode != "hebbian" && self.learning_mode != "stdp" {
+            return Err(format!(
+                "learning_mode must be 'hebbian' or 'stdp', got '{}'",
+                self.learning_mode
+            ));
+        }
+        Self::check_range("stdp_a_plus", self.stdp_a_plus, 0.0, 1.0)?;
+        Self::check_range("stdp_a_minus", self.stdp_a_minus, 0.0, 1.0)?;
+        for (key, v) in [("stdp_tau_plus", self.stdp_tau_plus), ("stdp_tau_minus", self.stdp_tau_minus)] {
+            if !v.is_finite() || v <= 0.0 {
+                return Err(format!("{} must be > 0 ticks, got {}", key, v));
             }
+        }
+        Ok(())
+    }
 
-            let sender_map = self.delta_index.entry(sender).or_default();
-            match sender_map.get(&receiver) {
-                Some((_, old_ts)) if *old_ts > timestamp => {}
-                _ => {
-                    sender_map.insert(receiver, (weight, timestamp));
-                }
-            }
+    fn env_key(key: &str) -> String {
+        format!("RAGP_{}", key.to_ascii_uppercase())
+    }
 
-            let next_tick = timestamp.saturating_add(1);
-            if next_tick > max_ts {
-                max_ts = next_tick;
+    fn overlay_env(&mut self) -> Result<(), String> {
+        for key in Self::KEYS {
+            if let Ok(raw) = env::var(Self::env_key(key)) {
+                self.apply(key, &ConfigValue::parse_raw(&raw))
+                    .map_err(|e| format!("{} ({})", e, Self::env_key(key)))?;
             }
         }
-        if max_ts > self.tick {
-            self.tick = max_ts;
+        Ok(())
+    }
+
+    /// Applies every key in `text` and returns the parsed table.
+    fn overlay_toml_str(&mut self, text: &str) -> Result<toml::Table, String> {
+        let table: toml::Table = text.parse().map_err(|e| format!("invalid TOML: {}", e))?;
+        for (key, value) in &table {
+            self.apply(key, &ConfigValue::from_toml(key, value)?)?;
         }
+        Ok(table)
     }
 
-    fn read_synapses_at(&self, offset: u64, count: u32) -> Vec<Synapse> {
-        if offset == u64::MAX || count == 0 {
-            return Vec::new();
+    fn overlay_toml_file(&mut self, path: &std::path::Path) -> Result<(), String> {
+        let text = fs::read_to_string(path)
+            .map_err(|e| format!("cannot read {}: {}", path.display(), e))?;
+        self.overlay_toml_str(&text)
+            .map(|_| ())
+            .map_err(|e| format!("{}: {}", path.display(), e))
+    }
+
+    fn overlay_kwargs(&mut self, kwargs: &Bound<'_, PyDict>) -> Result<(), String> {
+        for (k, v) in kwargs.iter() {
+            let key: String = k.extract().map_err(|_| "config keys must be str".to_string())?;
+            self.apply(&key, &ConfigValue::from_py(&key, &v)?)?;
         }
-        let mut f = if Self::is_chunk_offset(offset) {
-            let (chunk_start, local_offset) = Self::decode_chunk_offset(offset);
-            let path = self.chunk_file_path(chunk_start);
-            let Ok(mut file) = File::open(path) else {
-                return Vec::new();
-            };
-            if file.seek(SeekFrom::Start(local_offset)).is_err() {
-                return Vec::new();
-            }
-            file
-        } else {
-            // Legacy monolithic format fallback.
-            let Ok(mut file) = File::open(&self.base_path) else {
-                return Vec::new();
-            };
-            if file.seek(SeekFrom::Start(offset)).is_err() {
-                return Vec::new();
-            }
-            file
-        };
+        Ok(())
+    }
 
-        let mut synapses = Vec::with_capacity(count as usize);
-        for _ in 0..count {
-            let mut buf = [0_u8; SYNAPSE_SIZE as usize];
-            if f.read_exact(&mut buf).is_err() {
-                break;
-            }
-            let receiver_id = u64::from_le_bytes(buf[0..8].try_into().unwrap());
-            let weight = f32::from_le_bytes(buf[8..12].try_into().unwrap());
-            synapses.push(Synapse { receiver_id, weight });
+    /// Resolution order when no explicit config is given:
+    /// defaults -> `ragp_config.toml` in storage dir -> `RAGP_<KEY>` env vars.
+    /// Also returns the keys the file set, as the file had them; env overrides
+    /// are never part of it, so they are never written back.
+    fn resolve_for_storage(storage: &Storage) -> Result<(Self, toml::Table), String> {
+        let mut cfg = EngineConfig::default();
+        let mut stored = toml::Table::new();
+        if let Some(text) = storage.read_to_string(CONFIG_FILE_NAME) {
+            let file = cfg
+                .overlay_toml_str(&text)
+                .map_err(|e| format!("{}: {}", storage.display(CONFIG_FILE_NAME), e))?;
+            stored = file.keys().filter_map(|k| Some((k.clone(), cfg.get(k)?))).collect();
         }
-        synapses
+        cfg.overlay_env()?;
+        cfg.validate()?;
+        Ok((cfg, stored))
     }
 
-    fn load_from_base(&mut self, sender: u64) -> Vec<Synapse> {
-        let (offset, count) = match self.node_index.get(&sender) {
-            Some(meta) => (meta.synapse_offset, meta.synapse_count),
-            None => return Vec::new(),
-        };
-        self.read_synapses_at(offset, count)
+    fn toml_f32(v: f32) -> toml::Value {
+        // Go through the shortest decimal form so 0.3 is written as 0.3, not 0.30000001192092896.
+        toml::Value::Float(v.to_string().parse::<f64>().unwrap_or(v as f64))
     }
 
-    fn append_delta_entry(&self, entry: &DeltaEntry) {
-        let mut f = OpenOptions::new()
-            .create(true)
-            .append(true)
-            .open(&self.delta_path)
-            .expect("Gagal membuka delta.bin");
+    fn to_toml_table(&self) -> toml::Table {
+        Self::KEYS
+            .iter()
+            .filter_map(|key| Some((key.to_string(), self.get(key)?)))
+            .collect()
+    }
 
-        let mut payload = [0_u8; 24];
-        payload[0..8].copy_from_slice(&entry.sender_id.to_le_bytes());
-        payload[8..16].copy_from_slice(&entry.receiver_id.to_le_bytes());
-        payload[16..20].copy_from_slice(&entry.weight.to_le_bytes());
-        payload[20..24].copy_from_slice(&entry.timestamp.to_le_bytes());
-        let checksum = Self::crc32(&payload);
+    fn dynamics(&self) -> ActivationDynamics {
+        ActivationDynamics {
+            mode: self.activation_mode.clone(),
+            decay: self.activation_decay,
+            floor: self.activation_floor,
+            integration: Integration::parse(&self.integration).unwrap_or(Integration::Max),
+        }
+    }
 
-        f.write_all(&payload).unwrap();
-        f.write_all(&checksum.to_le_bytes()).unwrap();
+    fn save_table(table: &toml::Table, storage: &Storage) {
+        let body = toml::to_string(table).unwrap_or_default();
+        let text = format!("# RAGP engine config (auto-generated, editable)\n{}", body);
+        let _ = storage.write(CONFIG_FILE_NAME, text.as_bytes());
     }
+}
 
-    fn init_delta_if_needed(&self) {
-        if self.delta_path.exists() {
-            return;
+#[pymethods]
+impl EngineConfig {
+    #[new]
+    #[pyo3(signature = (**kwargs))]
+    fn py_new(kwargs: Option<&Bound<'_, PyDict>>) -> PyResult<Self> {
+        let mut cfg = EngineConfig::default();
+        if let Some(kw) = kwargs {
+            cfg.overlay_kwargs(kw).map_err(PyValueError::new_err)?;
         }
-        let mut f = File::create(&self.delta_path).expect("Gagal membuat delta.bin");
-        f.write_all(&MAGIC_DELTA.to_le_bytes()).unwrap();
-        f.write_all(&VERSION.to_le_bytes()).unwrap();
-        let reg = self.registry_version.min(u16::MAX as u32) as u16;
-        f.write_all(&reg.to_le_bytes()).unwrap();
+        cfg.validate().map_err(PyValueError::new_err)?;
+        Ok(cfg)
     }
 
-    fn synapse_count_for(&self, sender: u64) -> u32 {
-        let base = self.node_index.get(&sender).map_or(0, |m| m.synapse_count);
-        let delta = self.delta_index.get(&sender).map_or(0, |m| m.len() as u32);
-        base.saturating_add(delta)
+    #[staticmethod]
+    fn from_env() -> PyResult<Self> {
+        let mut cfg = EngineConfig::default();
+        cfg.overlay_env().map_err(PyValueError::new_err)?;
+        cfg.validate().map_err(PyValueError::new_err)?;
+        Ok(cfg)
     }
 
-    fn write_base_manifest_and_chunks(&mut self, all_data: &[(u64, Vec<Synapse>)]) {
-        self.clear_chunk_files();
-
-        let mut chunk_buffers: HashMap<u64, Vec<u8>> = HashMap::new();
-        let mut records: Vec<(u64, u32, u64, f32, u32)> = Vec::new();
+    #[staticmethod]
+    fn from_toml(path: String) -> PyResult<Self> {
+        let mut cfg = EngineConfig::default();
+        cfg.overlay_toml_file(std::path::Path::new(&path))
+            .map_err(PyValueError::new_err)?;
+        cfg.validate().map_err(PyValueError::new_err)?;
+        Ok(cfg)
+    }
 
-        for (node_id, synapses) in all_data {
-            let threshold = self
-                .node_index
-                .get(node_id)
-                .map_or(DEFAULT_THRESHOLD, |m| m.threshold);
+    #[pyo3(signature = (**kwargs))]
+    fn replace(&self, kwargs: Option<&Bound<'_, PyDict>>) -> PyResult<Self> {
+        let mut cfg = self.clone();
+        if let Some(kw) = kwargs {
+            cfg.overlay_kwargs(kw).map_err(PyValueError::new_err)?;
+        }
+        cfg.validate().map_err(PyValueError::new_err)?;
+        Ok(cfg)
+    }
 
-            if synapses.is_empty() {
-                records.push((*node_id, 0, u64::MAX, threshold, 0));
-                continue;
-            }
+    fn to_toml(&self) -> String {
+        toml::to_string(&self.to_toml_table()).unwrap_or_default()
+    }
 
-            let chunk_start = Self::chunk_start_for_sender(*node_id);
+    fn to_dict(&self) -> PyResult<PyObject> {
+        Python::with_gil(|py| {
+            let out = PyDict::new_bound(py);
+            for (key, value) in self.to_toml_table() {
+                match value {
+                    toml::Value::Integer(v) => out.set_item(key, v)?,
+                    toml::Value::Float(v) => out.set_item(key, v)?,
+                    toml::Value::String(v) => out.set_item(key, v)?,
+                    toml::Value::Boolean(v) => out.set_item(key, v)?,
+                    _ => {}
+                }
+            }
+            Ok(out.to_object(py))
+        })
+    }
+
+    fn __repr__(&self) -> String {
+        format!(
+            "EngineConfig(learning_mode='{}', max_synapses_per_node={}, temporal_window_size={}, max_spread_depth={}, cache_policy='{}', chunk_span={})",
+            self.learning_mode,
+            self.max_synapses_per_node,
+            self.temporal_window_size,
+            self.max_spread_depth,
+            self.cache_policy,
+            self.chunk_span
+        )
+    }
+}
+
+/// Working state a spread writes and learning reads: activation, temporal window,
+/// traces, the last settle report and TD eligibility. The engine keeps the default
+/// one in its own fields; every `ActivationContext` carries another.
+#[derive(Default)]
+struct WorkingState {
+    activation: HashMap<u64, f32>,
+    activation_tick: u32,
+    temporal_window: VecDeque<(u64, f32, u32)>,
+    stdp_tick: Option<u32>,
+    trace: HashMap<u64, ActivationTrace>,
+    last_settle: Option<SettleReport>,
+    td: TdState,
+}
+
+/// What `commit_fork` merges besides the journal: node values, drives (`None`
+/// once cleared in the fork) and config keys the fork changed.
+#[derive(Default)]
+struct ForkChanges {
+    values: Vec<(u64, f32)>,
+    drives: Vec<(String, Option<DriveState>)>,
+    config: Vec<(&'static str, ConfigValue)>,
+}
+
+/// One delta-log record as `append_delta_record` writes it; `kind` and `flags`
+/// are the raw bytes 24 and 25.
+#[derive(Clone, Copy, Debug)]
+struct DeltaRecord {
+    a: u64,
+    b: u64,
+    weight: f32,
+    ts: u32,
+    kind: u8,
+    flags: u8,
+}
+
+/// Overlay bookkeeping of an engine returned by `fork()`. Its delta writes land
+/// in `journal` instead of delta.bin until `commit_fork` replays them on the parent.
+struct ForkState {
+    journal: RefCell<Vec<DeltaRecord>>,
+    /// Parent tick and learned-ID cursor when the fork was taken.
+    tick: u32,
+    next_learned_id: u64,
+    /// Parent's non-zero node values, drives and config when the fork was taken;
+    /// `commit_fork` merges what the fork changed relative to them.
+    values: HashMap<u64, f32>,
+    drives: HashMap<String, DriveState>,
+    config: EngineConfig,
+    /// Set by `commit_fork` or `discard`; a closed fork holds no graph.
+    closed: bool,
+}
+
+/// Independent working state over a shared graph (`engine.new_context(name)`).
+/// Pass it as `ctx` to spread, settle, select, reward, Cd and window-learning
+/// calls; the graph, node values, drives, tick and delta log stay shared.
+#[pyclass]
+struct ActivationContext {
+    #[pyo3(get)]
+    name: String,
+    /// `engine_id` of the engine that created the context; only it accepts it.
+    engine_id: u64,
+    state: WorkingState,
+}
+
+/// A context's working state swapped into an engine. Dropping it swaps the
+/// states back, so an `Err` or a panic inside the call cannot leave the engine
+/// running on the context's state.
+struct SwappedContext<'a> {
+    engine: &'a mut RagpEngine,
+    state: &'a mut WorkingState,
+}
+
+impl Drop for SwappedContext<'_> {
+    fn drop(&mut self) {
+        self.engine.swap_working_state(self.state);
+    }
+}
+
+#[pymethods]
+impl ActivationContext {
+    fn get_active_nodes(&self) -> Vec<(u64, f32)> {
+        let mut out: Vec<(u64, f32)> =
+            self.state.activation.iter().map(|(k, v)| (*k, *v)).collect();
+        out.sort_by(|a, b| b.1.partial_cmp(&a.1).unwrap_or(std::cmp::Ordering::Equal));
+        out
+    }
+
+    /// (node, strength, tick) entries, oldest first.
+    fn get_temporal_window(&self) -> Vec<(u64, f32, u32)> {
+        self.state.temporal_window.iter().copied().collect()
+    }
+
+    fn reward_prediction_error(&self) -> Option<f32> {
+        self.state.td.last_rpe
+    }
+
+    /// Drops activation, window, traces and eligibility.
+    fn clear(&mut self) {
+        self.state = WorkingState::default();
+    }
+
+    fn __repr__(&self) -> String {
+        format!(
+            "ActivationContext(name='{}', active={}, window={})",
+            self.name,
+            self.state.activation.len(),
+            self.state.temporal_window.len()
+        )
+    }
+}
+
+/// Read-only view of a `RagpEngine` (`engine.query_view()`). Every method maps
+/// to a `peek_*` query, so it never changes counters, tick, activation or learning
+/// state; only the engine's `peeks` counter moves.
+#[pyclass]
+struct QueryView {
+    engine: Py<RagpEngine>,
+}
+
+#[pymethods]
+impl QueryView {
+    fn get_connections(
+        &self,
+        py: Python<'_>,
+        sender: u64,
+        kind: Option<String>,
+    ) -> PyResult<Vec<(u64, f32)>> {
+        self.engine.borrow(py).peek_connections(sender, kind)
+    }
+
+    fn compute_cd(
+        &self,
+        py: Python<'_>,
+        stimulus: u64,
+        context: Vec<u64>,
+    ) -> PyResult<Vec<(u64, f64)>> {
+        self.engine.borrow(py).peek_cd(stimulus, context)
+    }
+
+    fn spread(
+        &self,
+        py: Python<'_>,
+        seeds: Vec<(u64, f32)>,
+        integration: Option<String>,
+    ) -> PyResult<Vec<(u64, f32)>> {
+        self.engine.borrow(py).peek_spread(seeds, integration)
+    }
+
+    fn recall(
+        &self,
+        py: Python<'_>,
+        partial_nodes: Vec<u64>,
+        top_k: Option<usize>,
+    ) -> PyResult<PyObject> {
+        self.engine.borrow(py).recall(partial_nodes, top_k)
+    }
+
+    fn plan(
+        &self,
+        py: Python<'_>,
+        stimulus: u64,
+        context: Vec<u64>,
+        horizon: Option<u32>,
+        beam_width: Option<u32>,
+    ) -> PyResult<PyObject> {
+        self.engine.borrow(py).plan(stimulus, context, horizon, beam_width)
+    }
+}
+
+/// Where the engine's files live: a storage directory, or a file name -> bytes
+/// map for `RagpEngine.in_memory()`. Both hold the same files in the same
+/// formats; a memory store is shared with the engine's forks.
+#[derive(Clone)]
+enum Storage {
+    Disk(PathBuf),
+    Memory(Arc<Mutex<FileMap>>),
+}
+
+type FileMap = BTreeMap<String, Vec<u8>>;
+
+/// Sequential reader over one stored file.
+trait StorageRead: Read + Seek {}
+
+impl<T: Read + Seek> StorageRead for T {}
+
+/// Read-write handle for patching a stored file in place.
+trait StorageEdit: Read + Write + Seek {}
+
+impl<T: Read + Write + Seek> StorageEdit for T {}
+
+impl Storage {
+    fn memory() -> Self {
+        Storage::Memory(Arc::new(Mutex::new(BTreeMap::new())))
+    }
+
+    fn files(map: &Mutex<FileMap>) -> std::sync::MutexGuard<'_, FileMap> {
+        map.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    fn display(&self, name: &str) -> String {
+        match self {
+            Storage::Disk(dir) => dir.join(name).display().to_string(),
+            Storage::Memory(_) => format!("memory:{}", name),
+        }
+    }
+
+    fn exists(&self, name: &str) -> bool {
+        match self {
+            Storage::Disk(dir) => dir.join(name).exists(),
+            Storage::Memory(map) => Self::files(map).contains_key(name),
+        }
+    }
+
+    fn len(&self, name: &str) -> Option<u64> {
+        match self {
+            Storage::Disk(dir) => fs::metadata(dir.join(name)).ok().map(|m| m.len()),
+            Storage::Memory(map) => Self::files(map).get(name).map(|b| b.len() as u64),
+        }
+    }
+
+    /// Names of all stored files, ascending.
+    fn list(&self) -> Vec<String> {
+        match self {
+            Storage::Disk(dir) => {
+                let Ok(entries) = fs::read_dir(dir) else {
+                    return Vec::new();
+                };
+                let mut names: Vec<String> = entries
+                    .flatten()
+                    .filter(|e| e.file_type().is_ok_and(|t| t.is_file()))
+                    .map(|e| e.file_name().to_string_lossy().to_string())
+                    .collect();
+                names.sort();
+                names
+            }
+            Storage::Memory(map) => Self::files(map).keys().cloned().collect(),
+        }
+    }
+
+    /// Streaming reader for large files (the delta log, the base manifest).
+    fn open(&self, name: &str) -> Option<Box<dyn StorageRead>> {
+        match self {
+            Storage::Disk(dir) => {
+                File::open(dir.join(name)).ok().map(|f| Box::new(f) as Box<dyn StorageRead>)
+            }
+            Storage::Memory(map) => Self::files(map)
+                .get(name)
+                .map(|b| Box::new(Cursor::new(b.clone())) as Box<dyn StorageRead>),
+        }
+    }
+
+    fn read(&self, name: &str) -> Option<Vec<u8>> {
+        match self {
+            Storage::Disk(dir) => fs::read(dir.join(name)).ok(),
+            Storage::Memory(map) => Self::files(map).get(name).cloned(),
+        }
+    }
+
+    fn read_to_string(&self, name: &str) -> Option<String> {
+        self.read(name).and_then(|b| String::from_utf8(b).ok())
+    }
+
+    /// Up to `len` bytes from `offset`; shorter when the file ends first.
+    fn read_range(&self, name: &str, offset: u64, len: usize) -> Option<Vec<u8>> {
+        match self {
+            Storage::Disk(dir) => {
+                let mut f = File::open(dir.join(name)).ok()?;
+                f.seek(SeekFrom::Start(offset)).ok()?;
+                let mut out = Vec::with_capacity(len);
+                f.take(len as u64).read_to_end(&mut out).ok()?;
+                Some(out)
+            }
+            Storage::Memory(map) => {
+                let files = Self::files(map);
+                let bytes = files.get(name)?;
+                let start = (offset as usize).min(bytes.len());
+                let end = start.saturating_add(len).min(bytes.len());
+                Some(bytes[start..end].to_vec())
+            }
+        }
+    }
+
+    /// Creates or replaces a file.
+    fn write(&self, name: &str, bytes: &[u8]) -> std::io::Result<()> {
+        match self {
+            Storage::Disk(dir) => fs::write(dir.join(name), bytes),
+            Storage::Memory(map) => {
+                Self::files(map).insert(name.to_string(), bytes.to_vec());
+                Ok(())
+            }
+        }
+    }
+
+    /// Edits an existing file in place: a read-write file on disk, the buffer itself in memory.
+    fn patch(
+        &self,
+        name: &str,
+        edit: impl FnOnce(&mut dyn StorageEdit) -> std::io::Result<()>,
+    ) -> std::io::Result<()> {
+        match self {
+            Storage::Disk(dir) => {
+                edit(&mut OpenOptions::new().read(true).write(true).open(dir.join(name))?)
+            }
+            Storage::Memory(map) => {
+                let mut files = Self::files(map);
+                let bytes = files.get_mut(name).ok_or(std::io::ErrorKind::NotFound)?;
+                edit(&mut Cursor::new(bytes))
+            }
+        }
+    }
+
+    fn append(&self, name: &str, bytes: &[u8]) -> std::io::Result<()> {
+        match self {
+            Storage::Disk(dir) => {
+                OpenOptions::new().create(true).append(true).open(dir.join(name))?.write_all(bytes)
+            }
+            Storage::Memory(map) => {
+                Self::files(map).entry(name.to_string()).or_default().extend_from_slice(bytes);
+                Ok(())
+            }
+        }
+    }
+
+    fn remove(&self, name: &str) {
+        match self {
+            Storage::Disk(dir) => {
+                let _ = fs::remove_file(dir.join(name));
+            }
+            Storage::Memory(map) => {
+                Self::files(map).remove(name);
+            }
+        }
+    }
+}
+
+/// Source of `RagpEngine::engine_id`, so a context can tell which engine made it.
+static NEXT_ENGINE_ID: AtomicU64 = AtomicU64::new(1);
+
+#[pyclass]
+struct RagpEngine {
+    /// Process-unique; every engine and fork gets its own.
+    engine_id: u64,
+    storage: Storage,
+    node_index: HashMap<u64, NodeMeta>,
+    delta_index: HashMap<u64, HashMap<u64, DeltaEdge>>,
+    /// Format of the loaded base manifest and its chunks (`BASE_VERSION` once rewritten).
+    base_format: u16,
+    /// Tick of the consolidation that wrote the current base (0 when unknown).
+    consolidated_tick: u32,
+    /// Edges tombstoned by `saturation_policy` since the engine was opened.
+    evictions: u64,
+    /// Side-effect-free queries served (peek_*, recall, QueryView); the only thing
+    /// a peek changes, hence atomic behind `&self`.
+    peeks: AtomicU64,
+    /// Learned nodes added since the last consolidation (only in the delta log).
+    pending_nodes: HashSet<u64>,
+    /// Nodes retired since the last consolidation; their base edges are hidden
+    /// and their IDs cannot be re-added until the base is rewritten.
+    retired_nodes: HashSet<u64>,
+    next_learned_id: u64,
+    activation: HashMap<u64, f32>,
+    activation_tick: u32,
+    temporal_window: VecDeque<(u64, f32, u32)>,
+    /// Newest window tick STDP has already folded in; a pair counts again only
+    /// once its later spike is newer than this.
+    stdp_tick: Option<u32>,
+    tick: u32,
+
+    // Hybrid cache: pinned + LRU
+    base_cache: LruCache<u64, Vec<Synapse>>,
+    pinned_cache: HashMap<u64, Vec<Synapse>>,
+    pinned_set: HashSet<u64>,
+    access_count: HashMap<u64, u32>,
+    access_since_recompute: u32,
+
+    // Tunable parameters (cache policy, learning, limits)
+    config: EngineConfig,
+    /// What ragp_config.toml holds: the file as opened, an explicit config, and
+    /// keys changed by setters since. Env overrides never land here.
+    stored_config: toml::Table,
+    last_consolidation: ConsolidationReport,
+    last_settle: Option<SettleReport>,
+    trace: HashMap<u64, ActivationTrace>,
+    rng_state: u64,
+    drives: HashMap<String, DriveState>,
+    /// Node values changed since `values.bin` was last written.
+    values_dirty: bool,
+    td: TdState,
+    concepts: HashMap<u64, Concept>,
+    /// Co-activation support per candidate member set (sorted IDs), with the
+    /// newest firing already counted for it; not persisted.
+    concept_candidates: HashMap<Vec<u64>, (u32, Option<FiringStamp>)>,
+
+    // Computed cache budgets/metrics
+    cache_budget_bytes: u64,
+    pinned_budget_bytes: u64,
+    lru_budget_bytes: u64,
+    cache_bytes_est: u64,
+    pinned_bytes_est: u64,
+    lru_bytes_est: u64,
+    registry_version: u32,
+    loaded_registry_version: u32,
+    async_state: AsyncRuntimeState,
+    async_runtime: Option<AsyncActorRuntime>,
+    /// Cloned into every fork; while a clone is alive the base must not be rewritten.
+    base_lease: Arc<()>,
+    fork: Option<ForkState>,
+}
+
+impl RagpEngine {
+    fn crc32(data: &[u8]) -> u32 {
+        crc32fast::hash(data)
+    }
+
+    fn env_u32(key: &str, default: u32) -> u32 {
+        env::var(key)
+            .ok()
+            .and_then(|v| v.parse::<u32>().ok())
+            .unwrap_or(default)
+    }
+
+    fn default_shard_count() -> usize {
+        let cpus = std::thread::available_parallelism()
+            .map(|n| n.get())
+            .unwrap_or(4);
+        (cpus / 2).max(2)
+    }
+
+    fn default_async_state() -> AsyncRuntimeState {
+        AsyncRuntimeState {
+            enabled: false,
+            ingress_paused: false,
+            shard_count: Self::default_shard_count(),
+            global_queue_len: 0,
+            dropped_total: 0,
+            coalesced_total: 0,
+            hop_total: 0,
+            processed_total: 0,
+            processed_per_sec: 0.0,
+            guard_mode: "normal".to_string(),
+            per_shard_queue_len: vec![0; Self::default_shard_count()],
+            per_shard_processed: vec![0; Self::default_shard_count()],
+            policy: AsyncPolicy {
+                ram_warn_mb: DEFAULT_ASYNC_RAM_WARN_MB,
+                ram_critical_mb: DEFAULT_ASYNC_RAM_CRITICAL_MB,
+                coalesce_window_ms: DEFAULT_ASYNC_COALESCE_WINDOW_MS,
+                write_throttle_per_sec: DEFAULT_ASYNC_WRITE_THROTTLE_PER_SEC,
+            },
+        }
+    }
+
+    fn owner_shard(&self, sender: u64) -> usize {
+        if self.async_state.shard_count == 0 {
+            return 0;
+        }
+        (sender as usize) % self.async_state.shard_count
+    }
+
+    fn refresh_async_guard_mode(&mut self) {
+        let mut sys = System::new();
+        sys.refresh_memory();
+        let avail_bytes = Self::normalize_available_bytes(sys.available_memory());
+        let avail_mb = avail_bytes / (1024 * 1024);
+
+        self.async_state.guard_mode = if avail_mb <= self.async_state.policy.ram_critical_mb {
+            "critical".to_string()
+        } else if avail_mb <= self.async_state.policy.ram_warn_mb {
+            "warn".to_string()
+        } else {
+            "normal".to_string()
+        };
+    }
+
+    fn now_ms() -> u64 {
+        use std::time::{SystemTime, UNIX_EPOCH};
+        match SystemTime::now().duration_since(UNIX_EPOCH) {
+            Ok(d) => d.as_millis() as u64,
+            Err(_) => 0,
+        }
+    }
+
+    fn build_async_snapshot(&mut self) -> (HashMap<u64, Vec<AsyncSynapse>>, HashMap<u64, f32>) {
+        let mut senders: Vec<u64> = self.node_index.keys().copied().collect();
+        senders.sort_unstable();
+
+        let mut adjacency: HashMap<u64, Vec<AsyncSynapse>> = HashMap::new();
+        for sender in senders {
+            let conns = self.get_connections_internal(sender);
+            let syns: Vec<AsyncSynapse> = conns
+                .into_iter()
+                .map(|(receiver_id, weight)| AsyncSynapse { receiver_id, weight })
+                .collect();
+            adjacency.insert(sender, syns);
+        }
+
+        let mut thresholds: HashMap<u64, f32> = HashMap::new();
+        for (node, meta) in &self.node_index {
+            thresholds.insert(*node, meta.threshold);
+        }
+        (adjacency, thresholds)
+    }
+
+    fn sync_async_state_from_shared(&mut self) {
+        let Some(ar) = self.async_runtime.as_ref() else {
+            return;
+        };
+        let snapshot = ar.rt.block_on(async {
+            let s = ar.shared.lock().await;
+            (
+                s.ingress_paused,
+                s.global_queue_len,
+                s.processed_total,
+                s.processed_per_sec,
+                s.dropped_total,
+                s.coalesced_total,
+                s.hop_total,
+                s.guard_mode.clone(),
+                s.per_shard_queue_len.clone(),
+                s.per_shard_processed.clone(),
+            )
+        });
+
+        self.async_state.ingress_paused = snapshot.0;
+        self.async_state.global_queue_len = snapshot.1;
+        self.async_state.processed_total = snapshot.2;
+        self.async_state.processed_per_sec = snapshot.3;
+        self.async_state.dropped_total = snapshot.4;
+        self.async_state.coalesced_total = snapshot.5;
+        self.async_state.hop_total = snapshot.6;
+        self.async_state.guard_mode = snapshot.7;
+        self.async_state.per_shard_queue_len = snapshot.8;
+        self.async_state.per_shard_processed = snapshot.9;
+    }
+
+    fn clamp_f32(v: f32, lo: f32, hi: f32) -> f32 {
+        v.clamp(lo, hi)
+    }
+
+    fn normalize_available_bytes(raw: u64) -> u64 {
+        // Sysinfo versions differ (bytes vs KiB). Normalize to bytes heuristically.
+        if raw < (1_u64 << 31) {
+            raw.saturating_mul(1024)
+        } else {
+            raw
+        }
+    }
+
+    fn node_cache_bytes_from_len(len: usize) -> u64 {
+        (len as u64).saturating_mul(SYNAPSE_SIZE).saturating_add(64)
+    }
+
+    fn chunk_start_for_sender(&self, sender: u64) -> u64 {
+        if sender == 0 {
+            return 1;
+        }
+        let span = self.config.chunk_span;
+        ((sender - 1) / span) * span + 1
+    }
+
+    fn chunk_end_from_start(&self, start: u64) -> u64 {
+        start.saturating_add(self.config.chunk_span - 1)
+    }
+
+    fn chunk_file_name(&self, start: u64) -> String {
+        let end = self.chunk_end_from_start(start);
+        format!("base_{:06}_{:06}.bin", start, end)
+    }
+
+    fn chunk_file_resolved(&self, start: u64) -> String {
+        let name = self.chunk_file_name(start);
+        if self.storage.exists(&name) {
+            return name;
+        }
+        // Chunks written under a different chunk_span keep their own file name
+        // until the next rebuild; resolve them by start id.
+        let prefix = format!("base_{:06}_", start);
+        self.storage
+            .list()
+            .into_iter()
+            .find(|n| n.starts_with(&prefix) && n.ends_with(".bin"))
+            .unwrap_or(name)
+    }
+
+    fn encode_chunk_offset(chunk_start: u64, local_offset: u32) -> u64 {
+        OFFSET_CHUNK_FLAG | (chunk_start << 32) | u64::from(local_offset)
+    }
+
+    fn is_chunk_offset(encoded: u64) -> bool {
+        (encoded & OFFSET_CHUNK_FLAG) != 0
+    }
+
+    fn decode_chunk_offset(encoded: u64) -> (u64, u64) {
+        let chunk_start = (encoded & !OFFSET_CHUNK_FLAG) >> 32;
+        let local_offset = encoded & 0xFFFF_FFFF;
+        (chunk_start, local_offset)
+    }
+
+    fn chunk_file_starts(&self) -> Vec<u64> {
+        let mut out: Vec<u64> = Vec::new();
+        for name in self.storage.list() {
+            if !name.starts_with("base_") || !name.ends_with(".bin") {
+                continue;
+            }
+            let raw = name.trim_end_matches(".bin");
+            let parts: Vec<&str> = raw.split('_').collect();
+            if parts.len() != 3 {
+                continue;
+            }
+            if let Ok(start) = parts[1].parse::<u64>() {
+                out.push(start);
+            }
+        }
+        out.sort_unstable();
+        out
+    }
+
+    fn clear_chunk_files(&self) {
+        let starts = self.chunk_file_starts();
+        for start in starts {
+            self.storage.remove(&self.chunk_file_resolved(start));
+        }
+    }
+
+    fn has_chunk_files(&self) -> bool {
+        !self.chunk_file_starts().is_empty()
+    }
+
+    fn refresh_cache_budget(&mut self) {
+        let mut sys = System::new();
+        sys.refresh_memory();
+
+        let avail_raw = sys.available_memory();
+        let avail_bytes = Self::normalize_available_bytes(avail_raw);
+
+        let fraction = Self::clamp_f32(self.config.cache_ram_fraction, 0.01, 0.90);
+        let min_bytes = self.config.cache_ram_min_mb.saturating_mul(1024 * 1024);
+        let max_bytes = self.config.cache_ram_max_mb.saturating_mul(1024 * 1024).max(min_bytes);
+
+        let mut target = ((avail_bytes as f64) * (fraction as f64)) as u64;
+        if target < min_bytes {
+            target = min_bytes;
+        }
+        if target > max_bytes {
+            target = max_bytes;
+        }
+
+        self.cache_budget_bytes = target;
+        if self.config.cache_policy == "pinned_lru" {
+            let pin_fraction = Self::clamp_f32(self.config.cache_pin_fraction, 0.05, 0.90);
+            self.pinned_budget_bytes = ((target as f64) * (pin_fraction as f64)) as u64;
+            self.lru_budget_bytes = target.saturating_sub(self.pinned_budget_bytes);
+        } else {
+            self.pinned_budget_bytes = 0;
+            self.lru_budget_bytes = target;
+        }
+
+        self.enforce_cache_budget();
+    }
+
+    fn recount_cache_bytes(&mut self) {
+        self.pinned_bytes_est = self
+            .pinned_cache
+            .values()
+            .map(|v| Self::node_cache_bytes_from_len(v.len()))
+            .sum();
+        self.lru_bytes_est = self
+            .base_cache
+            .iter()
+            .map(|(_, v)| Self::node_cache_bytes_from_len(v.len()))
+            .sum();
+        self.cache_bytes_est = self.pinned_bytes_est.saturating_add(self.lru_bytes_est);
+    }
+    fn pinned_score_from_synapses(&self, node_id: u64, synapses: &[Synapse], max_access: f32) -> f32 {
+        let max_weight = synapses
+            .iter()
+            .fold(0.0_f32, |acc, s| acc.max(s.weight.abs()));
+        let access = self.access_count.get(&node_id).copied().unwrap_or(0) as f32;
+        let access_norm = if max_access <= 0.0 { 0.0 } else { access / max_access };
+        // Long-reinforced edges make a node worth pinning; a fresh Hebbian edge doesn't.
+        let reinforced = synapses.iter().map(|s| s.update_count).max().unwrap_or(0) as f32;
+        let reinforce_norm = reinforced / (reinforced + PINNED_REINFORCE_HALF);
+        0.5 * max_weight + 0.3 * access_norm + 0.2 * reinforce_norm
+    }
+
+    fn lowest_scored_pinned_cached(&self) -> Option<u64> {
+        let max_access = self.access_count.values().copied().max().unwrap_or(1) as f32;
+        let mut worst: Option<(u64, f32)> = None;
+        for (node_id, synapses) in &self.pinned_cache {
+            let score = self.pinned_score_from_synapses(*node_id, synapses, max_access);
+            match worst {
+                Some((_, wscore)) if score >= wscore => {}
+                _ => worst = Some((*node_id, score)),
+            }
+        }
+        worst.map(|(id, _)| id)
+    }
+
+    fn enforce_cache_budget(&mut self) {
+        self.recount_cache_bytes();
+
+        while self.lru_bytes_est > self.lru_budget_bytes {
+            if self.base_cache.pop_lru().is_none() {
+                break;
+            }
+            self.recount_cache_bytes();
+        }
+
+        if self.config.cache_policy == "pinned_lru" {
+            while self.pinned_bytes_est > self.pinned_budget_bytes {
+                let Some(victim) = self.lowest_scored_pinned_cached() else {
+                    break;
+                };
+                self.pinned_cache.remove(&victim);
+                self.pinned_set.remove(&victim);
+                self.recount_cache_bytes();
+            }
+        }
+
+        while self.cache_bytes_est > self.cache_budget_bytes {
+            if self.base_cache.pop_lru().is_some() {
+                self.recount_cache_bytes();
+                continue;
+            }
+            let Some(victim) = self.lowest_scored_pinned_cached() else {
+                break;
+            };
+            self.pinned_cache.remove(&victim);
+            self.pinned_set.remove(&victim);
+            self.recount_cache_bytes();
+        }
+    }
+
+    fn get_cached_or_load_base(&mut self, sender: u64) -> Vec<Synapse> {
+        if self.config.cache_policy == "pinned_lru" {
+            if let Some(v) = self.pinned_cache.get(&sender) {
+                return v.clone();
+            }
+        }
+
+        if let Some(v) = self.base_cache.get(&sender) {
+            return v.clone();
+        }
+
+        let loaded = self.load_from_base(sender);
+        if self.config.cache_policy == "pinned_lru" && self.pinned_set.contains(&sender) {
+            self.pinned_cache.insert(sender, loaded.clone());
+        } else {
+            self.base_cache.put(sender, loaded.clone());
+        }
+        self.enforce_cache_budget();
+        loaded
+    }
+
+    fn invalidate_sender_cache(&mut self, sender: u64) {
+        self.pinned_cache.remove(&sender);
+        self.base_cache.pop(&sender);
+        self.enforce_cache_budget();
+    }
+
+    fn record_access(&mut self, sender: u64) {
+        let entry = self.access_count.entry(sender).or_insert(0);
+        *entry = entry.saturating_add(1);
+        self.access_since_recompute = self.access_since_recompute.saturating_add(1);
+        if self.access_since_recompute >= CACHE_RECOMPUTE_ACCESS_INTERVAL {
+            self.access_since_recompute = 0;
+            self.refresh_cache_budget();
+            self.recompute_pinned_set(false);
+        }
+    }
+
+    fn recompute_pinned_set(&mut self, eager_warm: bool) {
+        if self.config.cache_policy != "pinned_lru" {
+            // Demote instead of dropping so a policy switch keeps warm entries.
+            self.pinned_set.clear();
+            for (key, v) in std::mem::take(&mut self.pinned_cache) {
+                self.base_cache.put(key, v);
+            }
+            self.enforce_cache_budget();
+            return;
+        }
+
+        let max_access = self.access_count.values().copied().max().unwrap_or(1) as f32;
+        let node_ids: Vec<u64> = self.node_index.keys().copied().collect();
+        let mut scored: Vec<(u64, f32, u64)> = Vec::with_capacity(node_ids.len());
+
+        for node_id in node_ids {
+            let synapses = if let Some(v) = self.pinned_cache.get(&node_id) {
+                v.clone()
+            } else if let Some(v) = self.base_cache.get(&node_id) {
+                v.clone()
+            } else {
+                self.load_from_base(node_id)
+            };
+            let score = self.pinned_score_from_synapses(node_id, &synapses, max_access);
+            let est = Self::node_cache_bytes_from_len(synapses.len());
+            scored.push((node_id, score, est));
+        }
+
+        scored.sort_by(|a, b| b.1.partial_cmp(&a.1).unwrap_or(std::cmp::Ordering::Equal));
+
+        let mut new_pinned: HashSet<u64> = HashSet::new();
+        let mut used: u64 = 0;
+        for (node_id, _, est) in scored {
+            let next = used.saturating_add(est);
+            if !new_pinned.is_empty() && next > self.pinned_budget_bytes {
+                continue;
+            }
+            if new_pinned.is_empty() && est > self.pinned_budget_bytes {
+                new_pinned.insert(node_id);
+                used = est;
+                continue;
+            }
+            if next <= self.pinned_budget_bytes {
+                new_pinned.insert(node_id);
+                used = next;
+            }
+        }
+        self.pinned_set = new_pinned;
+
+        let old_pinned_keys: Vec<u64> = self.pinned_cache.keys().copied().collect();
+        for key in old_pinned_keys {
+            if !self.pinned_set.contains(&key) {
+                if let Some(v) = self.pinned_cache.remove(&key) {
+                    self.base_cache.put(key, v);
+                }
+            }
+        }
+
+        let keys_to_promote: Vec<u64> = self.pinned_set.iter().copied().collect();
+        for key in keys_to_promote {
+            if self.pinned_cache.contains_key(&key) {
+                continue;
+            }
+            if let Some(v) = self.base_cache.pop(&key) {
+                self.pinned_cache.insert(key, v);
+                continue;
+            }
+            if eager_warm {
+                let loaded = self.load_from_base(key);
+                self.pinned_cache.insert(key, loaded);
+            }
+        }
+
+        self.enforce_cache_budget();
+    }
+    fn load_node_index(&mut self) {
+        self.node_index.clear();
+        self.loaded_registry_version = DEFAULT_INNATE_REGISTRY_VERSION;
+        let Some(mut f) = self.storage.open(BASE_FILE_NAME) else {
+            return;
+        };
+
+        let mut header = [0_u8; BASE_HEADER_SIZE as usize];
+        if f.read_exact(&mut header[..BASE_HEADER_SIZE_V2 as usize]).is_err() {
+            return;
+        }
+
+        let magic = u32::from_le_bytes(header[0..4].try_into().unwrap());
+        if magic != MAGIC_BASE {
+            return;
+        }
+        let version = u16::from_le_bytes(header[4..6].try_into().unwrap());
+        if !(VERSION..=BASE_VERSION).contains(&version) {
+            return;
+        }
+        self.base_format = version;
+        self.consolidated_tick = 0;
+        if version == BASE_VERSION {
+            if f.read_exact(&mut header[BASE_HEADER_SIZE_V2 as usize..]).is_err() {
+                return;
+            }
+            self.consolidated_tick = u32::from_le_bytes(header[14..18].try_into().unwrap());
+            // Delta is empty right after a consolidation, so the clock resumes from here.
+            self.tick = self.tick.max(self.consolidated_tick);
+        }
+        let reg = u32::from_le_bytes(header[10..14].try_into().unwrap());
+        if reg > 0 {
+            self.loaded_registry_version = reg;
+        }
+
+        let node_count = u32::from_le_bytes(header[6..10].try_into().unwrap());
+        for _ in 0..node_count {
+            let mut rec = [0_u8; NODE_INDEX_SIZE as usize];
+            if f.read_exact(&mut rec).is_err() {
+                break;
+            }
+            let node_id = u64::from_le_bytes(rec[0..8].try_into().unwrap());
+            let synapse_count = u32::from_le_bytes(rec[8..12].try_into().unwrap());
+            let synapse_offset = u64::from_le_bytes(rec[12..20].try_into().unwrap());
+            let threshold = f32::from_le_bytes(rec[20..24].try_into().unwrap());
+            let checksum = u32::from_le_bytes(rec[24..28].try_into().unwrap());
+            let role = NodeRole::from_code(u32::from_le_bytes(rec[28..32].try_into().unwrap()));
+            self.node_index.insert(
+                node_id,
+                NodeMeta {
+                    node_id,
+                    synapse_count,
+                    synapse_offset,
+                    threshold,
+                    checksum,
+                    role,
+                    value: 0.0,
+                },
+            );
+        }
+        self.next_learned_id = self.next_learned_after();
+    }
+
+    /// First free learned ID above every registered learned node.
+    fn next_learned_after(&self) -> u64 {
+        self.node_index
+            .keys()
+            .filter(|id| **id >= LEARNED_NODE_ID_START)
+            .max()
+            .map_or(LEARNED_NODE_ID_START, |id| id.saturating_add(1))
+    }
+
+    fn load_delta_index(&mut self) {
+        let Some(mut f) = self.storage.open(DELTA_FILE_NAME) else {
+            return;
+        };
+
+        let mut header = [0_u8; DELTA_HEADER_SIZE as usize];
+        if f.read_exact(&mut header).is_err() {
+            return;
+        }
+        let magic = u32::from_le_bytes(header[0..4].try_into().unwrap());
+        if magic != MAGIC_DELTA {
+            return;
+        }
+        let version = u16::from_le_bytes(header[4..6].try_into().unwrap());
+        let entry_size = match version {
+            DELTA_VERSION => DELTA_ENTRY_SIZE,
+            VERSION => DELTA_ENTRY_SIZE_V1,
+            _ => return,
+        };
+        let delta_registry_version = u16::from_le_bytes(header[6..8].try_into().unwrap()) as u32;
+        if delta_registry_version != self.registry_version {
+            return;
+        }
+
+        let Some(file_size) = self.storage.len(DELTA_FILE_NAME) else {
+            return;
+        };
+        if file_size <= DELTA_HEADER_SIZE {
+            if version != DELTA_VERSION {
+                self.reset_delta_file();
+            }
+            return;
+        }
+
+        let entry_count = (file_size - DELTA_HEADER_SIZE) / entry_size;
+        let payload_len = entry_size as usize - 4;
+        let mut max_ts = self.tick;
+        for _ in 0..entry_count {
+            let mut raw = [0_u8; DELTA_ENTRY_SIZE as usize];
+            if f.read_exact(&mut raw[..entry_size as usize]).is_err() {
+                break;
+            }
+
+            let payload = &raw[0..payload_len];
+            let checksum =
+                u32::from_le_bytes(raw[payload_len..payload_len + 4].try_into().unwrap());
+            if Self::crc32(payload) != checksum {
+                continue;
+            }
+
+            let sender = u64::from_le_bytes(raw[0..8].try_into().unwrap());
+            let receiver = u64::from_le_bytes(raw[8..16].try_into().unwrap());
+            let weight = f32::from_le_bytes(raw[16..20].try_into().unwrap());
+            let timestamp = u32::from_le_bytes(raw[20..24].try_into().unwrap());
+            max_ts = max_ts.max(timestamp.saturating_add(1));
+            let (kind, flags) = if version == DELTA_VERSION {
+                (raw[24], raw[25])
+            } else {
+                (SynapseKind::Association.code(), 0)
+            };
+            self.replay_delta_record(&DeltaRecord {
+                a: sender,
+                b: receiver,
+                weight,
+                ts: timestamp,
+                kind,
+                flags,
+            });
+        }
+        if max_ts > self.tick {
+            self.tick = max_ts;
+        }
+        if version != DELTA_VERSION {
+            self.rewrite_delta_file();
+        }
+    }
+
+    /// Opens (or initialises) an engine over `storage`; shared by `new`,
+    /// `in_memory` and `load_from`.
+    fn open_storage(storage: Storage, config: Option<EngineConfig>) -> PyResult<Self> {
+        let (config, stored_config) = match config {
+            Some(cfg) => {
+                cfg.validate().map_err(PyValueError::new_err)?;
+                let stored = cfg.to_toml_table();
+                EngineConfig::save_table(&stored, &storage);
+                (cfg, stored)
+            }
+            None => EngineConfig::resolve_for_storage(&storage).map_err(PyValueError::new_err)?,
+        };
+
+        let capacity = NonZeroUsize::new(config.lru_capacity.max(1)).unwrap();
+
+        let mut engine = RagpEngine {
+            engine_id: NEXT_ENGINE_ID.fetch_add(1, Ordering::Relaxed),
+            storage,
+            node_index: HashMap::new(),
+            delta_index: HashMap::new(),
+            base_format: BASE_VERSION,
+            consolidated_tick: 0,
+            evictions: 0,
+            peeks: AtomicU64::new(0),
+            pending_nodes: HashSet::new(),
+            retired_nodes: HashSet::new(),
+            next_learned_id: LEARNED_NODE_ID_START,
+            activation: HashMap::new(),
+            activation_tick: 0,
+            temporal_window: VecDeque::new(),
+            stdp_tick: None,
+            tick: 0,
+            base_cache: LruCache::new(capacity),
+            pinned_cache: HashMap::new(),
+            pinned_set: HashSet::new(),
+            access_count: HashMap::new(),
+            access_since_recompute: 0,
+            config,
+            stored_config,
+            last_consolidation: ConsolidationReport::default(),
+            last_settle: None,
+            trace: HashMap::new(),
+            rng_state: rng_seed_from_clock(),
+            drives: HashMap::new(),
+            values_dirty: false,
+            td: TdState::default(),
+            concepts: HashMap::new(),
+            concept_candidates: HashMap::new(),
+            cache_budget_bytes: 0,
+            pinned_budget_bytes: 0,
+            lru_budget_bytes: 0,
+            cache_bytes_est: 0,
+            pinned_bytes_est: 0,
+            lru_bytes_est: 0,
+            registry_version: Self::env_u32(
+                "RAGP_INNATE_REGISTRY_VERSION",
+                DEFAULT_INNATE_REGISTRY_VERSION,
+            ),
+            loaded_registry_version: DEFAULT_INNATE_REGISTRY_VERSION,
+            async_state: Self::default_async_state(),
+            async_runtime: None,
+            base_lease: Arc::new(()),
+            fork: None,
+        };
+
+        engine.load_node_index();
+        engine.maybe_migrate_legacy_base_to_chunks();
+        engine.load_node_index();
+        engine.init_delta_if_needed();
+        engine.load_delta_index();
+        engine.load_drives();
+        engine.load_node_values();
+        engine.load_concepts();
+        engine.refresh_cache_budget();
+        engine.recompute_pinned_set(true);
+        Ok(engine)
+    }
+
+    /// Files `save_to` and `load_from` carry: the base manifest and chunks, the
+    /// delta log and the side files.
+    fn is_engine_file(name: &str) -> bool {
+        let named = [
+            BASE_FILE_NAME,
+            DELTA_FILE_NAME,
+            CONFIG_FILE_NAME,
+            DRIVES_FILE_NAME,
+            CONCEPTS_FILE_NAME,
+            VALUES_FILE_NAME,
+        ];
+        named.contains(&name) || (name.starts_with("base_") && name.ends_with(".bin"))
+    }
+
+    /// False for forks, whose delta log, side files and role edits stay in memory.
+    fn persists(&self) -> bool {
+        self.fork.is_none()
+    }
+
+    /// Rewriting base chunks would pull them from under a fork, so it is refused
+    /// on a fork and on a parent while any of its forks is alive.
+    fn check_base_writable(&self, what: &str) -> PyResult<()> {
+        if self.fork.is_some() {
+            return Err(PyValueError::new_err(format!(
+                "{}: a fork reads its parent's base; commit_fork it and retry on the parent",
+                what
+            )));
+        }
+        let forks = Arc::strong_count(&self.base_lease) - 1;
+        if forks > 0 {
+            return Err(PyValueError::new_err(format!(
+                "{}: {} live fork(s) share the base; commit_fork or discard them first",
+                what, forks
+            )));
+        }
+        Ok(())
+    }
+
+    /// Empties a fork after `commit_fork` or `discard` and releases its base
+    /// lease so the parent may consolidate again.
+    fn close_fork(&mut self) {
+        let Some(state) = self.fork.as_mut() else {
+            return;
+        };
+        state.closed = true;
+        state.journal.get_mut().clear();
+        self.base_lease = Arc::new(());
+        self.node_index.clear();
+        self.delta_index.clear();
+        self.pending_nodes.clear();
+        self.retired_nodes.clear();
+        self.activation.clear();
+        self.trace.clear();
+        self.temporal_window.clear();
+        self.stdp_tick = None;
+        self.last_settle = None;
+        self.td = TdState::default();
+        self.concepts.clear();
+        self.concept_candidates.clear();
+        self.base_cache.clear();
+        self.pinned_cache.clear();
+        self.pinned_set.clear();
+        self.access_count.clear();
+        self.cache_bytes_est = 0;
+        self.pinned_bytes_est = 0;
+        self.lru_bytes_est = 0;
+    }
+
+    fn save_config(&self) {
+        if self.persists() {
+            EngineConfig::save_table(&self.stored_config, &self.storage);
+        }
+    }
+
+    /// Applies `updates` to a copy of the config and swaps it in once the whole
+    /// config validates; the runtime setters all go through here. Only the keys
+    /// set here are added to `stored_config`.
+    fn update_config(&mut self, updates: &[(&str, ConfigValue)]) -> PyResult<()> {
+        let mut cfg = self.config.clone();
+        for (key, value) in updates {
+            cfg.apply(key, value).map_err(PyValueError::new_err)?;
+        }
+        cfg.validate().map_err(PyValueError::new_err)?;
+        for (key, _) in updates {
+            if let Some(value) = cfg.get(key) {
+                self.stored_config.insert(key.to_string(), value);
+            }
+        }
+        self.config = cfg;
+        self.save_config();
+        Ok(())
+    }
+
+    /// Folds one delta-log record into the working graph: a learned node added or
+    /// retired, or an edge write. Edges touching unknown nodes are skipped.
+    fn replay_delta_record(&mut self, rec: &DeltaRecord) {
+        let tombstone = rec.flags & DELTA_FLAG_TOMBSTONE != 0;
+        if rec.flags & DELTA_FLAG_NODE != 0 {
+            if tombstone {
+                self.apply_node_retire(rec.a);
+            } else {
+                let role = NodeRole::from_code(rec.b.min(u32::MAX as u64) as u32);
+                self.apply_node_add(rec.a, role, rec.weight);
+            }
+            return;
+        }
+        if !self.node_index.contains_key(&rec.a) || !self.node_index.contains_key(&rec.b) {
+            return;
+        }
+
+        let entry = DeltaEntry {
+            sender_id: rec.a,
+            receiver_id: rec.b,
+            weight: rec.weight,
+            timestamp: rec.ts,
+            kind: SynapseKind::from_code(rec.kind),
+            tombstone,
+        };
+        DeltaEdge::record(self.delta_index.entry(rec.a).or_default(), rec.b, &entry);
+    }
+
+    /// Rewrites delta.bin in the current format from `delta_index`. Each edge gets
+    /// as many entries as it has updates (the first at `first_ts`, the rest at its
+    /// newest timestamp), so replay restores its update count and age. Used to
+    /// upgrade a v1 log before anything is appended to it.
+    fn rewrite_delta_file(&self) {
+        self.reset_delta_file();
+        let mut senders: Vec<&u64> = self.delta_index.keys().collect();
+        senders.sort_unstable();
+        for sender in senders {
+            let mut edges: Vec<_> = self.delta_index[sender].iter().collect();
+            edges.sort_unstable_by_key(|(receiver, _)| **receiver);
+            for (receiver, edge) in edges {
+                let entry = |timestamp: u32, tombstone: bool| DeltaEntry {
+                    sender_id: *sender,
+                    receiver_id: *receiver,
+                    weight: edge.weight,
+                    timestamp,
+                    kind: edge.kind,
+                    tombstone,
+                };
+                if edge.removed || edge.reset {
+                    self.append_delta_entry(&entry(edge.first_ts, true));
+                }
+                if edge.removed {
+                    continue;
+                }
+                self.append_delta_entry(&entry(edge.first_ts, false));
+                for _ in 1..edge.updates {
+                    self.append_delta_entry(&entry(edge.timestamp, false));
+                }
+            }
+        }
+    }
+
+    fn read_synapses_at(&self, offset: u64, count: u32) -> Vec<Synapse> {
+        if offset == u64::MAX || count == 0 {
+            return Vec::new();
+        }
+        let record_size = match self.base_format {
+            VERSION => SYNAPSE_SIZE_V1,
+            BASE_VERSION => SYNAPSE_SIZE,
+            _ => SYNAPSE_SIZE_V2,
+        };
+        let len = count as usize * record_size as usize;
+        let bytes = if Self::is_chunk_offset(offset) {
+            let (chunk_start, local_offset) = Self::decode_chunk_offset(offset);
+            self.storage.read_range(&self.chunk_file_resolved(chunk_start), local_offset, len)
+        } else {
+            // Legacy monolithic format fallback.
+            self.storage.read_range(BASE_FILE_NAME, offset, len)
+        };
+        let Some(bytes) = bytes else {
+            return Vec::new();
+        };
+
+        let mut synapses = Vec::with_capacity(count as usize);
+        for record in bytes.chunks_exact(record_size as usize) {
+            let mut buf = [0_u8; SYNAPSE_SIZE as usize];
+            buf[..record.len()].copy_from_slice(record);
+            let receiver_id = u64::from_le_bytes(buf[0..8].try_into().unwrap());
+            let weight = f32::from_le_bytes(buf[8..12].try_into().unwrap());
+            let kind = SynapseKind::from_code(buf[12]);
+            synapses.push(Synapse {
+                receiver_id,
+                weight,
+                kind,
+                created_at: u32::from_le_bytes(buf[16..20].try_into().unwrap()),
+                last_updated: u32::from_le_bytes(buf[20..24].try_into().unwrap()),
+                update_count: u32::from_le_bytes(buf[24..28].try_into().unwrap()),
+            });
+        }
+        if !self.retired_nodes.is_empty() {
+            synapses.retain(|s| !self.retired_nodes.contains(&s.receiver_id));
+        }
+        synapses
+    }
+
+    fn load_from_base(&mut self, sender: u64) -> Vec<Synapse> {
+        let (offset, count) = match self.node_index.get(&sender) {
+            Some(meta) => (meta.synapse_offset, meta.synapse_count),
+            None => return Vec::new(),
+        };
+        self.read_synapses_at(offset, count)
+    }
+
+    fn append_delta_entry(&self, entry: &DeltaEntry) {
+        let flags = if entry.tombstone { DELTA_FLAG_TOMBSTONE } else { 0 };
+        self.append_delta_record(
+            entry.sender_id,
+            entry.receiver_id,
+            entry.weight,
+            entry.timestamp,
+            entry.kind.code(),
+            flags,
+        );
+    }
+
+    /// Logs a learned node's metadata (`meta = Some`) or its retirement.
+    fn append_node_record(&self, node: u64, meta: Option<(NodeRole, f32)>) {
+        match meta {
+            Some((role, threshold)) => self.append_delta_record(
+                node,
+                role.code() as u64,
+                threshold,
+                self.tick,
+                0,
+                DELTA_FLAG_NODE,
+            ),
+            None => self.append_delta_record(
+                node,
+                0,
+                0.0,
+                self.tick,
+                0,
+                DELTA_FLAG_NODE | DELTA_FLAG_TOMBSTONE,
+            ),
+        }
+    }
+
+    fn append_delta_record(&self, a: u64, b: u64, weight: f32, ts: u32, kind: u8, flags: u8) {
+        if let Some(fork) = &self.fork {
+            fork.journal.borrow_mut().push(DeltaRecord { a, b, weight, ts, kind, flags });
+            return;
+        }
+        // Byte 25 holds flags; bytes 26..28 are reserved (zero).
+        let mut record = [0_u8; DELTA_ENTRY_SIZE as usize];
+        let (payload, tail) = record.split_at_mut(DELTA_ENTRY_SIZE as usize - 4);
+        payload[0..8].copy_from_slice(&a.to_le_bytes());
+        payload[8..16].copy_from_slice(&b.to_le_bytes());
+        payload[16..20].copy_from_slice(&weight.to_le_bytes());
+        payload[20..24].copy_from_slice(&ts.to_le_bytes());
+        payload[24] = kind;
+        payload[25] = flags;
+        tail.copy_from_slice(&Self::crc32(payload).to_le_bytes());
+
+        self.storage.append(DELTA_FILE_NAME, &record).expect("Gagal menulis delta.bin");
+    }
+
+    /// Registers (or updates) a learned node that has no base record yet.
+    fn apply_node_add(&mut self, node: u64, role: NodeRole, threshold: f32) {
+        self.next_learned_id = self.next_learned_id.max(node.saturating_add(1));
+        if let Some(meta) = self.node_index.get_mut(&node) {
+            meta.role = role;
+            meta.threshold = threshold;
+            return;
+        }
+        self.node_index.insert(
+            node,
+            NodeMeta {
+                node_id: node,
+                synapse_count: 0,
+                synapse_offset: u64::MAX,
+                threshold,
+                checksum: 0,
+                role,
+                value: 0.0,
+            },
+        );
+        self.pending_nodes.insert(node);
+    }
+
+    /// Drops a node and every edge touching it from the working state; base
+    /// edges pointing at it stay on disk, hidden, until the next consolidation.
+    fn apply_node_retire(&mut self, node: u64) {
+        self.next_learned_id = self.next_learned_id.max(node.saturating_add(1));
+        if self.node_index.remove(&node).is_none() {
+            return;
+        }
+        self.pending_nodes.remove(&node);
+        self.retired_nodes.insert(node);
+        self.concept_candidates.retain(|set, _| !set.contains(&node));
+        self.retain_live_concepts();
+        self.delta_index.remove(&node);
+        self.delta_index.retain(|_, edges| {
+            edges.remove(&node);
+            !edges.is_empty()
+        });
+        self.activation.remove(&node);
+        self.trace.remove(&node);
+        self.temporal_window.retain(|(n, _, _)| *n != node);
+        self.access_count.remove(&node);
+        self.pinned_set.remove(&node);
+        self.pinned_cache.remove(&node);
+        self.base_cache.pop(&node);
+        for syns in self.pinned_cache.values_mut() {
+            syns.retain(|s| s.receiver_id != node);
+        }
+        for (_, syns) in self.base_cache.iter_mut() {
+            syns.retain(|s| s.receiver_id != node);
+        }
+    }
+
+    /// Pushes the current graph to a running async runtime after nodes change.
+    fn refresh_async_graph(&mut self) {
+        if self.async_runtime.is_none() {
+            return;
+        }
+        let (adjacency, threshold) = self.build_async_snapshot();
+        if let Some(runtime) = self.async_runtime.as_ref() {
+            runtime.rt.block_on(async {
+                let mut s = runtime.shared.lock().await;
+                s.activation.retain(|n, _| threshold.contains_key(n));
+                s.adjacency = adjacency;
+                s.threshold = threshold;
+            });
+        }
+    }
+
+    fn init_delta_if_needed(&self) {
+        if self.storage.exists(DELTA_FILE_NAME) {
+            return;
+        }
+        self.storage
+            .write(DELTA_FILE_NAME, &self.delta_header())
+            .expect("Gagal membuat delta.bin");
+    }
+
+    fn delta_header(&self) -> Vec<u8> {
+        let mut header = Vec::with_capacity(DELTA_HEADER_SIZE as usize);
+        header.extend_from_slice(&MAGIC_DELTA.to_le_bytes());
+        header.extend_from_slice(&DELTA_VERSION.to_le_bytes());
+        let reg = self.registry_version.min(u16::MAX as u32) as u16;
+        header.extend_from_slice(&reg.to_le_bytes());
+        header
+    }
+
+    /// Live outgoing edges of `sender` after merging: delta writes that overwrite a
+    /// base edge are not counted twice and tombstoned base edges are not counted.
+    fn synapse_count_for(&mut self, sender: u64) -> u32 {
+        let base_count = self.node_index.get(&sender).map_or(0, |m| m.synapse_count);
+        if !self.delta_index.contains_key(&sender) {
+            return base_count;
+        }
+        let base: HashSet<u64> =
+            self.get_cached_or_load_base(sender).iter().map(|s| s.receiver_id).collect();
+        let mut count = base.len() as i64;
+        for (receiver, edge) in &self.delta_index[&sender] {
+            match (base.contains(receiver), edge.removed) {
+                (false, false) => count += 1,
+                (true, true) => count -= 1,
+                _ => {}
+            }
+        }
+        count.max(0) as u32
+    }
+
+    /// Frees a slot for a new edge from `sender` when it is at
+    /// `max_synapses_per_node`, following `saturation_policy`. Only association
+    /// edges are evicted (as delta tombstones); returns false when the edge must
+    /// not be formed. `rooms` carries each sender's capacity across one formation
+    /// pass; a true return means the caller forms the `receiver` edge. Edges formed
+    /// earlier in the pass are not victims, so a fresh edge at `initial_weight` is
+    /// not evicted by the next one.
+    fn make_room_for(
+        &mut self,
+        sender: u64,
+        receiver: u64,
+        rooms: &mut HashMap<u64, EdgeRoom>,
+    ) -> bool {
+        let room = rooms.entry(sender).or_insert_with(|| {
+            let count = self.synapse_count_for(sender);
+            let free = self.config.max_synapses_per_node.saturating_sub(count);
+            EdgeRoom { free, ..EdgeRoom::default() }
+        });
+        if room.free > 0 {
+            room.free -= 1;
+            room.formed.insert(receiver);
+            return true;
+        }
+        if self.config.saturation_policy == "refuse" {
+            return false;
+        }
+        if room.victims.is_none() {
+            let mut order = self.eviction_order(sender);
+            order.retain(|r| !room.formed.contains(r));
+            room.victims = Some(order);
+        }
+        let Some(victim) = room.victims.as_mut().and_then(|v| v.pop()) else {
+            return false;
+        };
+        room.formed.insert(receiver);
+        self.write_delta_entry(DeltaEntry {
+            sender_id: sender,
+            receiver_id: victim,
+            weight: 0.0,
+            timestamp: self.tick,
+            kind: SynapseKind::Association,
+            tombstone: true,
+        });
+        self.evictions = self.evictions.saturating_add(1);
+        true
+    }
+
+    /// Association receivers of `sender`, last to first victim under
+    /// `saturation_policy`. Edges without the needed history (innate or pre-v3
+    /// edges read 0) are of unknown age, not the oldest, so they go after every
+    /// edge whose age is known.
+    fn eviction_order(&mut self, sender: u64) -> Vec<u64> {
+        let base = self.get_cached_or_load_base(sender);
+        let mut synapses: Vec<Synapse> = self
+            .fold_delta(sender, base)
+            .into_iter()
+            .filter(|s| s.kind == SynapseKind::Association)
+            .collect();
+        let by_weight = |a: &Synapse, b: &Synapse| {
+            a.weight.abs().partial_cmp(&b.weight.abs()).unwrap_or(std::cmp::Ordering::Equal)
+        };
+        // (unknown, tick): known ages sort first, oldest first.
+        let age_key = |s: &Synapse| match self.config.saturation_policy.as_str() {
+            "evict_oldest" => (!s.has_history() || s.created_at == 0, s.created_at),
+            _ => (!s.has_history(), s.last_updated),
+        };
+        match self.config.saturation_policy.as_str() {
+            "evict_oldest" | "evict_lru" => synapses
+                .sort_by(|a, b| age_key(a).cmp(&age_key(b)).then_with(|| by_weight(a, b))),
+            _ => synapses.sort_by(by_weight),
+        }
+        synapses.iter().rev().map(|s| s.receiver_id).collect()
+    }
+
+    fn write_base_manifest_and_chunks(&mut self, all_data: &[(u64, Vec<Synapse>)]) {
+        self.clear_chunk_files();
+
+        let mut chunk_buffers: HashMap<u64, Vec<u8>> = HashMap::new();
+        let mut records: Vec<(u64, u32, u64, f32, u32)> = Vec::new();
+
+        for (node_id, synapses) in all_data {
+            let threshold = self
+                .node_index
+                .get(node_id)
+                .map_or(self.config.default_threshold, |m| m.threshold);
+
+            if synapses.is_empty() {
+                records.push((*node_id, 0, u64::MAX, threshold, 0));
+                continue;
+            }
+
+            let chunk_start = self.chunk_start_for_sender(*node_id);
             let chunk_buf = chunk_buffers.entry(chunk_start).or_default();
             let local_offset = chunk_buf.len() as u64;
             if local_offset > u32::MAX as u64 {
                 panic!("Chunk offset overflow for sender {}", node_id);
             }
 
-            let mut syn_bytes: Vec<u8> = Vec::with_capacity(synapses.len() * SYNAPSE_SIZE as usize);
-            for s in synapses {
-                syn_bytes.extend_from_slice(&s.receiver_id.to_le_bytes());
-                syn_bytes.extend_from_slice(&s.weight.to_le_bytes());
+            let mut syn_bytes: Vec<u8> = Vec::with_capacity(synapses.len() * SYNAPSE_SIZE as usize);
+            for s in synapses {
+                syn_bytes.extend_from_slice(&s.receiver_id.to_le_bytes());
+                syn_bytes.extend_from_slice(&s.weight.to_le_bytes());
+                syn_bytes.extend_from_slice(&[s.kind.code(), 0, 0, 0]);
+                syn_bytes.extend_from_slice(&s.created_at.to_le_bytes());
+                syn_bytes.extend_from_slice(&s.last_updated.to_le_bytes());
+                syn_bytes.extend_from_slice(&s.update_count.to_le_bytes());
+            }
+            let checksum = Self::crc32(&syn_bytes);
+            chunk_buf.extend_from_slice(&syn_bytes);
+
+            let encoded_offset = Self::encode_chunk_offset(chunk_start, local_offset as u32);
+            records.push((*node_id, synapses.len() as u32, encoded_offset, threshold, checksum));
+        }
+
+        records.sort_by_key(|(node_id, _, _, _, _)| *node_id);
+        let node_count = records.len() as u32;
+
+        let mut manifest: Vec<u8> = Vec::new();
+        manifest.write_all(&MAGIC_BASE.to_le_bytes()).unwrap();
+        manifest.write_all(&BASE_VERSION.to_le_bytes()).unwrap();
+        manifest.write_all(&node_count.to_le_bytes()).unwrap();
+        manifest.write_all(&self.registry_version.to_le_bytes()).unwrap();
+        manifest.write_all(&self.consolidated_tick.to_le_bytes()).unwrap();
+        for (node_id, count, offset, threshold, checksum) in &records {
+            manifest.write_all(&node_id.to_le_bytes()).unwrap();
+            manifest.write_all(&count.to_le_bytes()).unwrap();
+            manifest.write_all(&offset.to_le_bytes()).unwrap();
+            manifest.write_all(&threshold.to_le_bytes()).unwrap();
+            manifest.write_all(&checksum.to_le_bytes()).unwrap();
+            let role = self.node_index.get(node_id).map_or(0, |m| m.role.code());
+            manifest.write_all(&role.to_le_bytes()).unwrap();
+        }
+
+        self.storage.write(BASE_FILE_NAME, &manifest).expect("Gagal menulis base manifest");
+
+        let mut chunk_starts: Vec<u64> = chunk_buffers.keys().copied().collect();
+        chunk_starts.sort_unstable();
+        for start in chunk_starts {
+            let name = self.chunk_file_resolved(start);
+            let buf = chunk_buffers.get(&start).map_or(&[][..], |b| &b[..]);
+            self.storage.write(&name, buf).expect("Gagal menulis chunk file");
+        }
+        self.base_format = BASE_VERSION;
+
+        for (node_id, count, offset, threshold, checksum) in records {
+            if let Some(meta) = self.node_index.get_mut(&node_id) {
+                meta.synapse_count = count;
+                meta.synapse_offset = offset;
+                meta.threshold = threshold;
+                meta.checksum = checksum;
+            }
+        }
+    }
+
+    fn maybe_migrate_legacy_base_to_chunks(&mut self) {
+        if self.node_index.is_empty() || self.has_chunk_files() {
+            return;
+        }
+
+        let mut has_legacy_offsets = false;
+        for meta in self.node_index.values() {
+            if meta.synapse_count > 0
+                && meta.synapse_offset != u64::MAX
+                && !Self::is_chunk_offset(meta.synapse_offset)
+            {
+                has_legacy_offsets = true;
+                break;
+            }
+        }
+        if !has_legacy_offsets {
+            return;
+        }
+
+        let mut node_ids: Vec<u64> = self.node_index.keys().copied().collect();
+        node_ids.sort_unstable();
+        let mut all_data: Vec<(u64, Vec<Synapse>)> = Vec::with_capacity(node_ids.len());
+        for node_id in node_ids {
+            all_data.push((node_id, self.load_from_base(node_id)));
+        }
+        self.write_base_manifest_and_chunks(&all_data);
+        println!("[Migrasi] base.bin lama dimigrasikan ke chunk range");
+    }
+    fn prune_strategy_for(
+        sender: u64,
+        global: &PruneStrategy,
+        ranges: &[(u64, u64, PruneStrategy)],
+    ) -> PruneStrategy {
+        ranges
+            .iter()
+            .find(|(start, end, _)| sender >= *start && sender <= *end)
+            .map(|(_, _, strategy)| strategy.clone())
+            .unwrap_or_else(|| global.clone())
+    }
+
+    fn rebuild_base_bin(&mut self) -> ConsolidationReport {
+        // Validated at config time; fall back to legacy behavior if that ever slips.
+        let global = PruneStrategy::parse(&self.config.prune_strategy)
+            .unwrap_or_else(|_| PruneStrategy::parse(DEFAULT_PRUNE_STRATEGY).unwrap());
+        let ranges = parse_prune_ranges(&self.config.prune_ranges).unwrap_or_default();
+        let mut report = ConsolidationReport {
+            tick: self.tick,
+            strategy: global.spec.clone(),
+            ranges: self.config.prune_ranges.clone(),
+            ..ConsolidationReport::default()
+        };
+
+        let node_ids: Vec<u64> = self.node_index.keys().copied().collect();
+        let mut all_data: Vec<(u64, Vec<Synapse>)> = Vec::new();
+
+        for node_id in &node_ids {
+            let base = self.load_from_base(*node_id);
+            let mut merged = self.fold_delta(*node_id, base);
+
+            if !merged.is_empty() {
+                report.decayed = report.decayed.saturating_add(self.decay_synapses(&mut merged));
+                let strategy = Self::prune_strategy_for(*node_id, &global, &ranges);
+                let ratio = self.config.prune_ratio;
+                let dropped = strategy.apply_by_kind(&mut merged, self.tick, ratio) as u32;
+                merged.sort_by(|a, b| b.weight.partial_cmp(&a.weight).unwrap_or(std::cmp::Ordering::Equal));
+
+                let slot = report.per_strategy.entry(strategy.spec).or_insert((0, 0));
+                slot.0 = slot.0.saturating_add(1);
+                slot.1 = slot.1.saturating_add(dropped);
+                report.pruned = report.pruned.saturating_add(dropped);
+                report.kept = report.kept.saturating_add(merged.len() as u64);
+            }
+
+            all_data.push((*node_id, merged));
+        }
+
+        all_data.sort_by_key(|(node_id, _)| *node_id);
+        self.consolidated_tick = self.tick;
+        self.write_base_manifest_and_chunks(&all_data);
+        self.pending_nodes.clear();
+        self.retired_nodes.clear();
+        report
+    }
+
+    /// Base synapses of `sender` with its delta writes folded in, history included:
+    /// a delta write moves `last_updated` and adds to `update_count`, an edge that
+    /// only exists in delta was created at its first write, and tombstoned edges
+    /// are dropped.
+    fn fold_delta(&self, sender: u64, mut synapses: Vec<Synapse>) -> Vec<Synapse> {
+        let Some(delta) = self.delta_index.get(&sender) else {
+            return synapses;
+        };
+        let position: HashMap<u64, usize> =
+            synapses.iter().enumerate().map(|(i, s)| (s.receiver_id, i)).collect();
+        let mut dropped: HashSet<u64> = HashSet::new();
+        for (receiver, edge) in delta {
+            if edge.removed {
+                dropped.insert(*receiver);
+                continue;
+            }
+            let fresh = Synapse {
+                created_at: edge.first_ts,
+                last_updated: edge.timestamp,
+                update_count: edge.updates,
+                ..Synapse::new(*receiver, edge.weight, edge.kind)
+            };
+            match position.get(receiver) {
+                Some(&i) if edge.reset => synapses[i] = fresh,
+                Some(&i) => {
+                    let existing = &mut synapses[i];
+                    existing.weight = edge.weight;
+                    existing.kind = edge.kind;
+                    existing.last_updated = edge.timestamp;
+                    existing.update_count = existing.update_count.saturating_add(edge.updates);
+                }
+                None => synapses.push(fresh),
+            }
+        }
+        if !dropped.is_empty() {
+            synapses.retain(|s| !dropped.contains(&s.receiver_id));
+        }
+        synapses
+    }
+
+    /// Consolidation-time decay of edges idle since their last write (or since the
+    /// previous consolidation, whichever is later): `w *= (1 - weight_decay)^(idle /
+    /// update_count)`, so long-reinforced edges fade slower than fresh ones. Edges
+    /// without history are left alone. Returns how many edges decayed.
+    fn decay_synapses(&self, synapses: &mut [Synapse]) -> u32 {
+        let rate = self.config.weight_decay;
+        if rate <= 0.0 {
+            return 0;
+        }
+        let mut decayed = 0_u32;
+        for s in synapses.iter_mut().filter(|s| s.has_history()) {
+            let idle = self.tick.saturating_sub(s.last_updated.max(self.consolidated_tick));
+            if idle == 0 {
+                continue;
+            }
+            s.weight *= (1.0 - rate).powf(idle as f32 / s.update_count as f32);
+            decayed = decayed.saturating_add(1);
+        }
+        decayed
+    }
+
+    fn init_node_pool_internal(&mut self, node_ids: Vec<u64>) {
+        self.node_index.clear();
+        self.delta_index.clear();
+        self.activation.clear();
+        self.trace.clear();
+        self.temporal_window.clear();
+        self.stdp_tick = None;
+        self.base_cache.clear();
+        self.pinned_cache.clear();
+        self.pinned_set.clear();
+        self.access_count.clear();
+        self.access_since_recompute = 0;
+        self.tick = 0;
+        self.consolidated_tick = 0;
+        self.pending_nodes.clear();
+        self.retired_nodes.clear();
+        self.next_learned_id = LEARNED_NODE_ID_START;
+        self.td = TdState::default();
+        self.concepts.clear();
+        self.concept_candidates.clear();
+        self.clear_chunk_files();
+        self.values_dirty = false;
+        self.storage.remove(VALUES_FILE_NAME);
+        self.storage.remove(CONCEPTS_FILE_NAME);
+
+        let mut sorted_ids = node_ids;
+        sorted_ids.sort_unstable();
+        sorted_ids.dedup();
+
+        for id in &sorted_ids {
+            self.node_index.insert(
+                *id,
+                NodeMeta {
+                    node_id: *id,
+                    synapse_count: 0,
+                    synapse_offset: u64::MAX,
+                    threshold: self.config.default_threshold,
+                    checksum: 0,
+                    role: NodeRole::Unassigned,
+                    value: 0.0,
+                },
+            );
+        }
+
+        let all_data: Vec<(u64, Vec<Synapse>)> = sorted_ids
+            .iter()
+            .map(|id| (*id, Vec::new()))
+            .collect();
+        self.write_base_manifest_and_chunks(&all_data);
+
+        self.reset_delta_file();
+        self.refresh_cache_budget();
+        self.recompute_pinned_set(true);
+
+        println!("[RagpEngine] {} node diinisialisasi (tanpa sinapsis)", self.node_index.len());
+    }
+
+    fn migrate_innate_registry(&mut self, node_ids: Vec<u64>) -> (u32, u32) {
+        let mut sorted_ids = node_ids;
+        sorted_ids.sort_unstable();
+        sorted_ids.dedup();
+        if sorted_ids.is_empty() {
+            return (0, 0);
+        }
+
+        if self.node_index.is_empty() {
+            self.init_node_pool_internal(sorted_ids);
+            self.loaded_registry_version = self.registry_version;
+            return (0, 0);
+        }
+
+        // Learned nodes are not part of the innate registry and carry over as-is.
+        sorted_ids.extend(self.node_index.keys().filter(|id| **id >= LEARNED_NODE_ID_START));
+        sorted_ids.sort_unstable();
+        let target_set: HashSet<u64> = sorted_ids.iter().copied().collect();
+        let old_ids: Vec<u64> = self.node_index.keys().copied().collect();
+        let old_set: HashSet<u64> = old_ids.iter().copied().collect();
+
+        let mut old_data: HashMap<u64, Vec<Synapse>> = HashMap::new();
+        for sender in &old_ids {
+            let base = self.load_from_base(*sender);
+            old_data.insert(*sender, self.fold_delta(*sender, base));
+        }
+
+        let old_meta: HashMap<u64, (NodeRole, f32, f32)> = self
+            .node_index
+            .iter()
+            .map(|(id, m)| (*id, (m.role, m.value, m.threshold)))
+            .collect();
+        self.node_index.clear();
+        for id in &sorted_ids {
+            let threshold = match old_meta.get(id) {
+                Some(m) if *id >= LEARNED_NODE_ID_START => m.2,
+                _ => self.config.default_threshold,
+            };
+            self.node_index.insert(
+                *id,
+                NodeMeta {
+                    node_id: *id,
+                    synapse_count: 0,
+                    synapse_offset: u64::MAX,
+                    threshold,
+                    checksum: 0,
+                    role: old_meta.get(id).map_or(NodeRole::Unassigned, |m| m.0),
+                    value: old_meta.get(id).map_or(0.0, |m| m.1),
+                },
+            );
+        }
+
+        let mut all_data: Vec<(u64, Vec<Synapse>)> = Vec::with_capacity(sorted_ids.len());
+        for id in &sorted_ids {
+            let mut syns = old_data.remove(id).unwrap_or_default();
+            syns.retain(|s| target_set.contains(&s.receiver_id));
+            syns.sort_by(|a, b| b.weight.partial_cmp(&a.weight).unwrap_or(std::cmp::Ordering::Equal));
+            all_data.push((*id, syns));
+        }
+        self.write_base_manifest_and_chunks(&all_data);
+
+        let removed_nodes = old_set.difference(&target_set).count() as u32;
+        let added_nodes = target_set.difference(&old_set).count() as u32;
+
+        self.delta_index.clear();
+        self.activation.clear();
+        self.trace.clear();
+        self.temporal_window.clear();
+        self.stdp_tick = None;
+        self.base_cache.clear();
+        self.pinned_cache.clear();
+        self.pinned_set.clear();
+        self.access_count.clear();
+        self.access_since_recompute = 0;
+        self.pending_nodes.clear();
+        self.retired_nodes.clear();
+        if self.retain_live_concepts() {
+            self.save_concepts();
+        }
+        self.reset_delta_file();
+        self.loaded_registry_version = self.registry_version;
+        self.refresh_cache_budget();
+        self.recompute_pinned_set(true);
+
+        (added_nodes, removed_nodes)
+    }
+
+    fn ensure_innate_registry_internal(
+        &mut self,
+        node_ids: Vec<u64>,
+    ) -> PyResult<(bool, u32, u32)> {
+        let mut sorted_ids = node_ids;
+        sorted_ids.sort_unstable();
+        sorted_ids.dedup();
+        if sorted_ids.is_empty() {
+            return Ok((false, 0, 0));
+        }
+
+        let mut current_ids: Vec<u64> = self
+            .node_index
+            .keys()
+            .copied()
+            .filter(|id| *id < LEARNED_NODE_ID_START)
+            .collect();
+        current_ids.sort_unstable();
+        let needs_migrate =
+            self.node_index.is_empty()
+                || self.loaded_registry_version != self.registry_version
+                || current_ids != sorted_ids;
+        if !needs_migrate {
+            return Ok((false, 0, 0));
+        }
+        self.check_base_writable("ensure_innate_registry")?;
+
+        let (added, removed) = self.migrate_innate_registry(sorted_ids);
+        Ok((true, added, removed))
+    }
+
+    fn push_window(&mut self, node: u64, strength: f32) {
+        self.temporal_window.push_back((node, strength, self.tick));
+        if self.temporal_window.len() > self.config.temporal_window_size {
+            self.temporal_window.pop_front();
+        }
+    }
+
+    fn spread_seeds(&mut self, seeds: &[(u64, f32)], integration: Integration) {
+        if self.config.spread_dynamics == "settle" {
+            let (max_iters, epsilon) = (self.config.settle_max_iters, self.config.settle_epsilon);
+            self.settle_seeds(seeds, integration, max_iters, epsilon);
+            return;
+        }
+        let scaled = self.drive_scaled_seeds(seeds);
+        let seeds = scaled.as_slice();
+        self.td.pending.extend(seeds.iter().map(|(n, _)| *n));
+        self.begin_wave();
+
+        let mut activation = std::mem::take(&mut self.activation);
+        let mut events: Vec<WaveEvent> = Vec::new();
+        let max_depth = self.config.max_spread_depth;
+        spread_wave(
+            &mut activation,
+            seeds,
+            integration,
+            max_depth,
+            |node| self.wave_edges(node),
+            |event| events.push(event),
+        );
+        self.activation = activation;
+        for event in events {
+            self.record_wave_event(event);
+        }
+
+        self.tick = self.tick.saturating_add(1);
+    }
+
+    /// Live edges of `sender` for a wave (counts an access, fills the cache).
+    fn wave_edges(&mut self, sender: u64) -> WaveEdges {
+        let connections = self.get_connections_internal(sender);
+        connections.into_iter().map(|(r, w)| (r, w, self.threshold_of(r))).collect()
+    }
+
+    /// `wave_edges` through `peek_typed_connections`, optionally of one kind only.
+    fn peek_wave_edges(&self, sender: u64, kind: Option<SynapseKind>) -> WaveEdges {
+        self.peek_typed_connections(sender)
+            .into_iter()
+            .filter(|(_, _, k)| kind.is_none_or(|want| *k == want))
+            .map(|(r, w, _)| (r, w, self.threshold_of(r)))
+            .collect()
+    }
+
+    fn threshold_of(&self, node: u64) -> f32 {
+        self.node_index.get(&node).map_or(self.config.default_threshold, |m| m.threshold)
+    }
+
+    /// Turns one live wave event into its trace and temporal-window entries.
+    fn record_wave_event(&mut self, event: WaveEvent) {
+        let tracing = self.config.trace_activation;
+        let tick = self.tick as u64;
+        match event {
+            WaveEvent::Seed { node, strength, level } => {
+                if tracing {
+                    self.trace.insert(node, ActivationTrace::seed(strength, level, tick, None));
+                }
+                self.push_window(node, strength);
+            }
+            WaveEvent::Fired { node, via, level, depth } => {
+                if let (true, Some((predecessor, weight, incoming))) = (tracing, via) {
+                    self.trace.insert(
+                        node,
+                        ActivationTrace {
+                            predecessor: Some(predecessor),
+                            weight,
+                            incoming,
+                            level,
+                            depth,
+                            tick,
+                            shard: None,
+                            via_hop: false,
+                        },
+                    );
+                }
+                self.push_window(node, level);
+            }
+            WaveEvent::Silenced { node } => {
+                // Inhibited to zero: its old path no longer explains anything.
+                self.trace.remove(&node);
+            }
+        }
+    }
+
+    fn load_drives(&mut self) {
+        self.drives.clear();
+        let Some(text) = self.storage.read_to_string(DRIVES_FILE_NAME) else {
+            return;
+        };
+        let Ok(table) = text.parse::<toml::Table>() else {
+            return;
+        };
+        for (name, entry) in table {
+            let node = entry.get("node").and_then(|v| v.as_integer());
+            let level = entry
+                .get("level")
+                .and_then(|v| v.as_float().or(v.as_integer().map(|i| i as f64)));
+            if let (Some(node), Some(level)) = (node, level) {
+                if node >= 0 {
+                    let level = (level as f32).clamp(0.0, 1.0);
+                    self.drives.insert(name, DriveState { node: node as u64, level });
+                }
+            }
+        }
+    }
+
+    fn save_drives(&self) {
+        if !self.persists() {
+            return;
+        }
+        let mut table = toml::Table::new();
+        for (name, drive) in &self.drives {
+            let mut entry = toml::Table::new();
+            entry.insert("node".into(), toml::Value::Integer(drive.node as i64));
+            entry.insert("level".into(), EngineConfig::toml_f32(drive.level));
+            table.insert(name.clone(), toml::Value::Table(entry));
+        }
+        let body = toml::to_string(&table).unwrap_or_default();
+        let text = format!("# RAGP homeostatic drives (auto-generated)\n{}", body);
+        let _ = self.storage.write(DRIVES_FILE_NAME, text.as_bytes());
+    }
+
+    /// Writes state whose persistence is deferred to consolidation.
+    fn flush_deferred(&mut self) {
+        if self.values_dirty {
+            self.save_node_values();
+        }
+    }
+
+    fn load_concepts(&mut self) {
+        self.concepts.clear();
+        let Some(text) = self.storage.read_to_string(CONCEPTS_FILE_NAME) else {
+            return;
+        };
+        let Ok(table) = text.parse::<toml::Table>() else {
+            return;
+        };
+        let int = |entry: &toml::Value, key: &str| entry.get(key).and_then(|v| v.as_integer());
+        for (id, entry) in table {
+            let Ok(node) = id.parse::<u64>() else {
+                continue;
+            };
+            let Some(members) = entry.get("members").and_then(|v| v.as_array()) else {
+                continue;
+            };
+            let members: Vec<u64> = members
+                .iter()
+                .filter_map(|m| m.as_integer())
+                .filter(|m| *m >= 0)
+                .map(|m| m as u64)
+                .collect();
+            let support = int(&entry, "support").unwrap_or(0).clamp(0, u32::MAX as i64) as u32;
+            let formed_at = int(&entry, "formed_at").unwrap_or(0).clamp(0, u32::MAX as i64) as u32;
+            self.concepts.insert(node, Concept { members, support, formed_at });
+        }
+        if self.retain_live_concepts() {
+            self.save_concepts();
+        }
+    }
+
+    fn save_concepts(&self) {
+        if !self.persists() {
+            return;
+        }
+        let mut table = toml::Table::new();
+        for (node, concept) in &self.concepts {
+            let mut entry = toml::Table::new();
+            let members = concept.members.iter().map(|m| toml::Value::Integer(*m as i64));
+            entry.insert("members".into(), toml::Value::Array(members.collect()));
+            entry.insert("support".into(), toml::Value::Integer(concept.support as i64));
+            entry.insert("formed_at".into(), toml::Value::Integer(concept.formed_at as i64));
+            table.insert(node.to_string(), toml::Value::Table(entry));
+        }
+        let body = toml::to_string(&table).unwrap_or_default();
+        let text = format!("# RAGP learned concepts (auto-generated)\n{}", body);
+        let _ = self.storage.write(CONCEPTS_FILE_NAME, text.as_bytes());
+    }
+
+    /// Drops retired members, and concepts whose node is gone or that are left
+    /// with fewer than two members. Returns whether anything changed.
+    fn retain_live_concepts(&mut self) -> bool {
+        let before: usize = self.concepts.values().map(|c| c.members.len() + 1).sum();
+        let index = &self.node_index;
+        for concept in self.concepts.values_mut() {
+            concept.members.retain(|m| index.contains_key(m));
+        }
+        self.concepts.retain(|id, c| index.contains_key(id) && c.members.len() >= 2);
+        let after: usize = self.concepts.values().map(|c| c.members.len() + 1).sum();
+        before != after
+    }
+
+    /// Counts co-activation support for every `concept_size` subset of the
+    /// strongest supra-threshold nodes in the temporal window (and the async
+    /// activation map when the runtime is on). A set reaching `concept_support`
+    /// becomes a concept node while fewer than `concept_max` exist. The window
+    /// outlives one call, so a set is counted again only once one of its members
+    /// has fired after the tick it was last counted at.
+    fn observe_coactivation(&mut self) -> Vec<u64> {
+        if self.config.concept_max == 0 {
+            return Vec::new();
+        }
+        let mut observed: Vec<(u64, f32, FiringStamp)> = self
+            .temporal_window
+            .iter()
+            .map(|&(n, s, tick)| (n, s, FiringStamp { tick, wave: 0 }))
+            .collect();
+        // The sync tick does not move on the async path, so async firings are
+        // stamped with the async wave counter instead.
+        if let Some(runtime) = self.async_runtime.as_ref() {
+            let (levels, wave) = runtime.rt.block_on(async {
+                let s = runtime.shared.lock().await;
+                let levels: Vec<(u64, f32)> = s.activation.iter().map(|(k, v)| (*k, *v)).collect();
+                (levels, s.wave_seq)
+            });
+            let stamp = FiringStamp { tick: 0, wave };
+            observed.extend(levels.into_iter().map(|(n, s)| (n, s, stamp)));
+        }
+        // node -> (strongest level, newest firing)
+        let mut strongest: HashMap<u64, (f32, FiringStamp)> = HashMap::new();
+        for (node, strength, stamp) in observed {
+            let Some(meta) = self.node_index.get(&node) else {
+                continue;
+            };
+            if strength < meta.threshold || self.concepts.contains_key(&node) {
+                continue;
+            }
+            let slot = strongest.entry(node).or_insert((0.0, stamp));
+            *slot = (slot.0.max(strength), slot.1.max(stamp));
+        }
+        let mut nodes: Vec<(u64, f32, FiringStamp)> =
+            strongest.into_iter().map(|(n, (s, t))| (n, s, t)).collect();
+        nodes.sort_by(|a, b| {
+            b.1.partial_cmp(&a.1).unwrap_or(std::cmp::Ordering::Equal).then(a.0.cmp(&b.0))
+        });
+        nodes.truncate(CONCEPT_CANDIDATE_NODES);
+        let last_fired: HashMap<u64, FiringStamp> =
+            nodes.iter().map(|(n, _, t)| (*n, *t)).collect();
+        let mut ids: Vec<u64> = last_fired.keys().copied().collect();
+        ids.sort_unstable();
+
+        let mut formed = Vec::new();
+        for set in combinations(&ids, self.config.concept_size) {
+            if self.concepts.values().any(|c| c.members == set) {
+                continue;
+            }
+            let seen = set.iter().fold(FiringStamp::default(), |acc, n| acc.max(last_fired[n]));
+            let (support, counted) =
+                self.concept_candidates.entry(set.clone()).or_insert((0, None));
+            if counted.is_some_and(|c| !seen.is_newer_than(c)) {
+                continue;
+            }
+            *counted = Some(counted.map_or(seen, |c| c.max(seen)));
+            *support = support.saturating_add(1);
+            let support = *support;
+            if support < self.config.concept_support
+                || self.concepts.len() >= self.config.concept_max as usize
+            {
+                continue;
+            }
+            match self.form_concept(set, support) {
+                Some(node) => formed.push(node),
+                None => break,
+            }
+        }
+        if self.concept_candidates.len() > CONCEPT_CANDIDATE_CAP {
+            self.concept_candidates.retain(|_, (support, _)| *support > 1);
+        }
+        if !formed.is_empty() {
+            self.save_concepts();
+            self.refresh_async_graph();
+        }
+        formed
+    }
+
+    /// Allocates a learned `internal` node for `members` and wires it both ways,
+    /// each edge subject to `max_synapses_per_node` and `saturation_policy`.
+    /// `None` once the learned ID range is exhausted.
+    fn form_concept(&mut self, members: Vec<u64>, support: u32) -> Option<u64> {
+        let node = self.next_learned_id;
+        if node >= LEARNED_NODE_ID_END {
+            return None;
+        }
+        let threshold = self.config.default_threshold;
+        self.append_node_record(node, Some((NodeRole::Internal, threshold)));
+        self.apply_node_add(node, NodeRole::Internal, threshold);
+        let ts = self.tick;
+        let kind = SynapseKind::Association;
+        let mut rooms: HashMap<u64, EdgeRoom> = HashMap::new();
+        for &member in &members {
+            if self.make_room_for(member, node, &mut rooms) {
+                self.write_delta_weight(member, node, CONCEPT_LINK_WEIGHT, ts, kind);
             }
-            let checksum = Self::crc32(&syn_bytes);
-            chunk_buf.extend_from_slice(&syn_bytes);
+            if self.make_room_for(node, member, &mut rooms) {
+                self.write_delta_weight(node, member, CONCEPT_LINK_WEIGHT, ts, kind);
+            }
+        }
+        self.concept_candidates.remove(&members);
+        println!(
+            "[Konsep] node {} dibentuk dari {:?} (support={})",
+            node, members, support
+        );
+        self.concepts.insert(node, Concept { members, support, formed_at: ts });
+        Some(node)
+    }
 
-            let encoded_offset = Self::encode_chunk_offset(chunk_start, local_offset as u32);
-            records.push((*node_id, synapses.len() as u32, encoded_offset, threshold, checksum));
+    fn node_value(&self, node: u64) -> f32 {
+        self.node_index.get(&node).map_or(0.0, |m| m.value)
+    }
+
+    /// V(s) of a feature set: mean of its node values (0 for an empty set).
+    fn state_value(&self, features: &[u64]) -> f32 {
+        if features.is_empty() {
+            return 0.0;
         }
+        features.iter().map(|n| self.node_value(*n)).sum::<f32>() / features.len() as f32
+    }
 
-        records.sort_by_key(|(node_id, _, _, _, _)| *node_id);
-        let node_count = records.len() as u32;
+    /// One accumulating-trace TD(lambda) step for `features` with error `delta`.
+    /// Returns how many node values changed.
+    fn td_update(&mut self, features: &[u64], delta: f32) -> usize {
+        let decay = self.config.td_gamma * self.config.td_lambda;
+        for trace in self.td.traces.values_mut() {
+            *trace *= decay;
+        }
+        if !features.is_empty() {
+            let grad = 1.0 / features.len() as f32;
+            for node in features {
+                *self.td.traces.entry(*node).or_insert(0.0) += grad;
+            }
+        }
+        let alpha = self.config.td_alpha;
+        let mut updated = 0;
+        for (node, trace) in &self.td.traces {
+            if let Some(meta) = self.node_index.get_mut(node) {
+                meta.value = (meta.value + alpha * delta * trace).clamp(-1.0, 1.0);
+                updated += 1;
+            }
+        }
+        self.td.traces.retain(|_, t| *t >= TD_TRACE_FLOOR);
+        updated
+    }
 
-        let mut manifest = File::create(&self.base_path).expect("Gagal menulis base manifest");
-        manifest.write_all(&MAGIC_BASE.to_le_bytes()).unwrap();
-        manifest.write_all(&VERSION.to_le_bytes()).unwrap();
-        manifest.write_all(&node_count.to_le_bytes()).unwrap();
-        manifest.write_all(&self.registry_version.to_le_bytes()).unwrap();
-        for (node_id, count, offset, threshold, checksum) in &records {
-            manifest.write_all(&node_id.to_le_bytes()).unwrap();
-            manifest.write_all(&count.to_le_bytes()).unwrap();
-            manifest.write_all(&offset.to_le_bytes()).unwrap();
-            manifest.write_all(&threshold.to_le_bytes()).unwrap();
-            manifest.write_all(&checksum.to_le_bytes()).unwrap();
-            manifest.write_all(&0_u32.to_le_bytes()).unwrap();
+    fn load_node_values(&mut self) {
+        let Some(bytes) = self.storage.read(VALUES_FILE_NAME) else {
+            return;
+        };
+        if bytes.len() < 14 {
+            return;
+        }
+        let magic = u32::from_le_bytes(bytes[0..4].try_into().unwrap());
+        let version = u16::from_le_bytes(bytes[4..6].try_into().unwrap());
+        let count = u32::from_le_bytes(bytes[6..10].try_into().unwrap()) as usize;
+        let body_end = 10 + count * 12;
+        if magic != MAGIC_VALUES || version != VALUES_VERSION || bytes.len() != body_end + 4 {
+            println!("[RagpEngine] values.bin tidak valid, nilai TD diabaikan");
+            return;
         }
+        let checksum = u32::from_le_bytes(bytes[body_end..body_end + 4].try_into().unwrap());
+        if Self::crc32(&bytes[10..body_end]) != checksum {
+            println!("[RagpEngine] values.bin checksum mismatch, nilai TD diabaikan");
+            return;
+        }
+        for rec in bytes[10..body_end].chunks_exact(12) {
+            let node = u64::from_le_bytes(rec[0..8].try_into().unwrap());
+            let value = f32::from_le_bytes(rec[8..12].try_into().unwrap());
+            if let Some(meta) = self.node_index.get_mut(&node) {
+                meta.value = value;
+            }
+        }
+    }
 
-        let mut chunk_starts: Vec<u64> = chunk_buffers.keys().copied().collect();
-        chunk_starts.sort_unstable();
-        for start in chunk_starts {
-            let path = self.chunk_file_path(start);
-            let mut f = File::create(path).expect("Gagal menulis chunk file");
-            if let Some(buf) = chunk_buffers.get(&start) {
-                f.write_all(buf).unwrap();
+    /// Writes non-zero node values: magic, version, count, (id, value)*, crc32.
+    fn save_node_values(&mut self) {
+        self.values_dirty = false;
+        if !self.persists() {
+            return;
+        }
+        let mut entries: Vec<(u64, f32)> = self
+            .node_index
+            .iter()
+            .filter(|(_, m)| m.value != 0.0)
+            .map(|(id, m)| (*id, m.value))
+            .collect();
+        entries.sort_by_key(|(id, _)| *id);
+
+        let mut body: Vec<u8> = Vec::with_capacity(entries.len() * 12);
+        for (id, value) in &entries {
+            body.extend_from_slice(&id.to_le_bytes());
+            body.extend_from_slice(&value.to_le_bytes());
+        }
+        let mut out: Vec<u8> = Vec::with_capacity(body.len() + 14);
+        out.extend_from_slice(&MAGIC_VALUES.to_le_bytes());
+        out.extend_from_slice(&VALUES_VERSION.to_le_bytes());
+        out.extend_from_slice(&(entries.len() as u32).to_le_bytes());
+        out.extend_from_slice(&body);
+        out.extend_from_slice(&Self::crc32(&body).to_le_bytes());
+        let _ = self.storage.write(VALUES_FILE_NAME, &out);
+    }
+
+    /// Seed strength scaled by the level of the drive bound to the seed node.
+    fn drive_scaled_strength(&self, node: u64, strength: f32) -> f32 {
+        match self.drives.values().find(|d| d.node == node) {
+            Some(drive) => strength * drive.level,
+            None => strength,
+        }
+    }
+
+    fn drive_scaled_seeds(&self, seeds: &[(u64, f32)]) -> Vec<(u64, f32)> {
+        seeds
+            .iter()
+            .map(|&(node, strength)| (node, self.drive_scaled_strength(node, strength)))
+            .collect()
+    }
+
+    /// Action -> (gain, drive name) for actions directly linked (positive association)
+    /// from a drive's node; the most urgent drive wins when several link in.
+    fn drive_gains(&self, conns: &HashMap<u64, TypedConnections>) -> HashMap<u64, (f32, String)> {
+        let mut gains: HashMap<u64, (f32, String)> = HashMap::new();
+        for (name, drive) in &self.drives {
+            let Some(edges) = conns.get(&drive.node) else {
+                continue;
+            };
+            for (action, weight, kind) in edges {
+                if *weight <= 0.0 || *kind != SynapseKind::Association {
+                    continue;
+                }
+                let slot = gains.entry(*action).or_insert((drive.level, name.clone()));
+                if drive.level > slot.0 {
+                    *slot = (drive.level, name.clone());
+                }
             }
         }
+        gains
+    }
 
-        for (node_id, count, offset, threshold, checksum) in records {
-            if let Some(meta) = self.node_index.get_mut(&node_id) {
-                meta.synapse_count = count;
-                meta.synapse_offset = offset;
-                meta.threshold = threshold;
-                meta.checksum = checksum;
+    /// Engine RNG (xorshift64): uniform in [0, 1), reproducible after `seed_rng`.
+    fn next_random(&mut self) -> f32 {
+        let mut x = self.rng_state;
+        x ^= x << 13;
+        x ^= x >> 7;
+        x ^= x << 17;
+        self.rng_state = x;
+        (x >> 40) as f32 / (1_u64 << 24) as f32
+    }
+
+    /// Lateral-inhibition competition: every candidate is driven by its clamped Cd
+    /// and suppressed by the summed activity of the others. Updates are synchronous
+    /// and leaky (halfway to the target) so the competition settles instead of
+    /// oscillating. Returns (iterations, converged).
+    fn run_action_competition(&self, candidates: &mut [ActionCandidate]) -> (u32, bool) {
+        for c in candidates.iter_mut() {
+            c.activity = c.drive.max(0.0);
+        }
+        let inhibition = self.config.select_inhibition;
+        for iter in 1..=self.config.select_iters {
+            let total: f32 = candidates.iter().map(|c| c.activity).sum();
+            let next: Vec<f32> = candidates
+                .iter()
+                .map(|c| {
+                    let target = (c.drive - inhibition * (total - c.activity)).max(0.0);
+                    0.5 * (c.activity + target)
+                })
+                .collect();
+            let mut delta = 0.0_f32;
+            for (c, level) in candidates.iter_mut().zip(next) {
+                delta = delta.max((level - c.activity).abs());
+                c.activity = level;
+            }
+            if delta < SELECT_EPSILON {
+                return (iter, true);
+            }
+        }
+        (self.config.select_iters, false)
+    }
+
+    /// Reset-or-decay step that starts every new wave.
+    fn begin_wave(&mut self) {
+        if self.config.dynamics().accumulates() {
+            self.decay_activation_to_tick();
+        } else {
+            self.activation.clear();
+            self.trace.clear();
+            self.activation_tick = self.tick;
+        }
+    }
+
+    fn swap_working_state(&mut self, state: &mut WorkingState) {
+        std::mem::swap(&mut self.activation, &mut state.activation);
+        std::mem::swap(&mut self.activation_tick, &mut state.activation_tick);
+        std::mem::swap(&mut self.temporal_window, &mut state.temporal_window);
+        std::mem::swap(&mut self.stdp_tick, &mut state.stdp_tick);
+        std::mem::swap(&mut self.trace, &mut state.trace);
+        std::mem::swap(&mut self.last_settle, &mut state.last_settle);
+        std::mem::swap(&mut self.td, &mut state.td);
+    }
+
+    /// Rejects a context created by another engine (or fork).
+    fn check_context(&self, ctx: &ActivationContext) -> PyResult<()> {
+        if ctx.engine_id != self.engine_id {
+            return Err(PyValueError::new_err(format!(
+                "context '{}' belongs to another engine",
+                ctx.name
+            )));
+        }
+        Ok(())
+    }
+
+    /// Runs `f` with `ctx`'s working state swapped in (the engine's own without one).
+    /// The swap is undone on return, error or panic.
+    fn in_context<R>(
+        &mut self,
+        ctx: Option<&mut ActivationContext>,
+        f: impl FnOnce(&mut Self) -> R,
+    ) -> PyResult<R> {
+        let Some(ctx) = ctx else {
+            return Ok(f(self));
+        };
+        self.check_context(ctx)?;
+        self.swap_working_state(&mut ctx.state);
+        let guard = SwappedContext {
+            engine: self,
+            state: &mut ctx.state,
+        };
+        Ok(f(&mut *guard.engine))
+    }
+
+    /// `settle_wave` over the live activation. Traces follow the final iteration
+    /// and the window follows first firing, seeds first, for STDP causality.
+    fn settle_seeds(
+        &mut self,
+        seeds: &[(u64, f32)],
+        integration: Integration,
+        max_iters: u32,
+        epsilon: f32,
+    ) -> SettleReport {
+        let scaled = self.drive_scaled_seeds(seeds);
+        let seeds = scaled.as_slice();
+        self.td.pending.extend(seeds.iter().map(|(n, _)| *n));
+        self.begin_wave();
+
+        let mut activation = std::mem::take(&mut self.activation);
+        let mut events: Vec<WaveEvent> = Vec::new();
+        let report = settle_wave(
+            &mut activation,
+            seeds,
+            integration,
+            max_iters,
+            epsilon,
+            |node| self.wave_edges(node),
+            |event| events.push(event),
+        );
+        self.activation = activation;
+        let activation = &self.activation;
+        self.trace.retain(|node, _| activation.contains_key(node));
+        for event in events {
+            self.record_wave_event(event);
+        }
+
+        self.tick = self.tick.saturating_add(1);
+        self.last_settle = Some(report.clone());
+        report
+    }
+
+    /// Read-only settling from the seeds alone: `settle_wave` over a local map with
+    /// edges from `peek_wave_edges` (of `kind` only, when given). Returns final
+    /// levels, the strongest excitatory sender of each fired node, and the report.
+    fn peek_settle(
+        &self,
+        seeds: &[(u64, f32)],
+        integration: Integration,
+        kind: Option<SynapseKind>,
+    ) -> (HashMap<u64, f32>, HashMap<u64, u64>, SettleReport) {
+        let mut activation: HashMap<u64, f32> = HashMap::new();
+        let mut via: HashMap<u64, u64> = HashMap::new();
+        let report = settle_wave(
+            &mut activation,
+            seeds,
+            integration,
+            self.config.settle_max_iters,
+            self.config.settle_epsilon,
+            |node| self.peek_wave_edges(node, kind),
+            |event| {
+                if let WaveEvent::Fired { node, via: Some((sender, _, _)), .. } = event {
+                    via.insert(node, sender);
+                }
+            },
+        );
+        (activation, via, report)
+    }
+
+    /// Read-only twin of one `spread_seeds` wave from a clean slate (as in `reset`
+    /// mode, whatever the configured mode): drive-scaled seeds, BFS or settling per
+    /// `spread_dynamics`, edges via `peek_wave_edges`.
+    fn peek_spread_levels(
+        &self,
+        seeds: &[(u64, f32)],
+        integration: Integration,
+    ) -> HashMap<u64, f32> {
+        let seeds = self.drive_scaled_seeds(seeds);
+        if self.config.spread_dynamics == "settle" {
+            return self.peek_settle(&seeds, integration, None).0;
+        }
+        let mut activation: HashMap<u64, f32> = HashMap::new();
+        spread_wave(
+            &mut activation,
+            &seeds,
+            integration,
+            self.config.max_spread_depth,
+            |node| self.peek_wave_edges(node, None),
+            |_| {},
+        );
+        activation
+    }
+
+    fn count_peek(&self) {
+        self.peeks.fetch_add(1, Ordering::Relaxed);
+    }
+
+    fn settle_report_dict(report: &SettleReport) -> PyResult<PyObject> {
+        Python::with_gil(|py| {
+            let out = PyDict::new_bound(py);
+            out.set_item("iterations", report.iterations)?;
+            out.set_item("converged", report.converged)?;
+            out.set_item("final_delta", report.final_delta)?;
+            out.set_item("active", report.active)?;
+            Ok(out.to_object(py))
+        })
+    }
+
+    /// Applies the decay owed for ticks elapsed since activation was last decayed.
+    fn decay_activation_to_tick(&mut self) {
+        let elapsed = self.tick.saturating_sub(self.activation_tick);
+        if elapsed > 0 {
+            let dynamics = self.config.dynamics();
+            decay_activation_map(&mut self.activation, &mut self.trace, elapsed as f32, &dynamics);
+        }
+        self.activation_tick = self.tick;
+    }
+
+    fn push_dynamics_to_async(&self) {
+        if let Some(runtime) = self.async_runtime.as_ref() {
+            let dynamics = self.config.dynamics();
+            let trace_enabled = self.config.trace_activation;
+            runtime.rt.block_on(async {
+                let mut s = runtime.shared.lock().await;
+                s.dynamics = dynamics;
+                s.trace_enabled = trace_enabled;
+                if !trace_enabled {
+                    s.trace.clear();
+                }
+            });
+        }
+    }
+
+    /// Scores every association receiver of `stimulus`, best first. Value is the
+    /// stimulus->action weight, cost the mean |weight| of the action's `cost` edges
+    /// and opportunity the mean `opportunity` context->action weight, with
+    /// `DEFAULT_CD_COST` / `DEFAULT_CD_OPPORTUNITY` standing in when there are none.
+    /// While action masking is active, receivers that are not action-role nodes are
+    /// marked `excluded`.
+    fn check_cd_inputs(&self, stimulus: u64, context: &[u64], label: &str) -> PyResult<()> {
+        let stimulus_label = format!("{}(stimulus)", label);
+        self.strict_check_node(stimulus, &stimulus_label)?;
+        self.strict_check_role(stimulus, &[NodeRole::Sensor, NodeRole::Homeostatic], &stimulus_label)?;
+        let context_label = format!("{}(context)", label);
+        for ctx in context {
+            self.strict_check_node(*ctx, &context_label)?;
+            self.strict_check_role(*ctx, &[NodeRole::Context, NodeRole::Sensor], &context_label)?;
+        }
+        Ok(())
+    }
+
+    fn cd_breakdown(
+        &mut self,
+        stimulus: u64,
+        context: &[u64],
+        label: &str,
+    ) -> PyResult<Vec<CdBreakdown>> {
+        self.check_cd_inputs(stimulus, context, label)?;
+        let drive_nodes: Vec<u64> = self.drives.values().map(|d| d.node).collect();
+        let conns = gather_cd_connections(stimulus, context, &drive_nodes, |node| {
+            self.get_typed_connections_internal(node)
+        });
+        Ok(self.score_cd(stimulus, context, &conns))
+    }
+
+    /// `select_action` on whichever working state is swapped in.
+    fn select_action_internal(
+        &mut self,
+        stimuli: Vec<u64>,
+        context: Vec<u64>,
+        temperature: Option<f32>,
+        epsilon: Option<f32>,
+    ) -> PyResult<PyObject> {
+        let temperature = temperature.unwrap_or(self.config.select_temperature);
+        let epsilon = epsilon.unwrap_or(self.config.select_epsilon);
+        if !temperature.is_finite() || temperature < 0.0 {
+            return Err(PyValueError::new_err(format!(
+                "temperature must be >= 0, got {}",
+                temperature
+            )));
+        }
+        if !(0.0..=1.0).contains(&epsilon) {
+            return Err(PyValueError::new_err(format!(
+                "epsilon must be in [0, 1], got {}",
+                epsilon
+            )));
+        }
+
+        let mut candidates: Vec<ActionCandidate> = Vec::new();
+        for stimulus in &stimuli {
+            for row in self.cd_breakdown(*stimulus, &context, "select_action")? {
+                if row.excluded.is_some() {
+                    continue;
+                }
+                match candidates.iter_mut().find(|c| c.action == row.action) {
+                    Some(existing) if existing.cd >= row.cd => {}
+                    Some(existing) => {
+                        existing.cd = row.cd;
+                        existing.stimulus = *stimulus;
+                    }
+                    None => candidates.push(ActionCandidate {
+                        action: row.action,
+                        stimulus: *stimulus,
+                        cd: row.cd,
+                        drive: 0.0,
+                        activity: 0.0,
+                    }),
+                }
+            }
+        }
+        for c in candidates.iter_mut() {
+            c.drive = Self::cd_drive(c.cd) as f32;
+        }
+
+        let (iterations, converged) = self.run_action_competition(&mut candidates);
+        candidates.sort_by(|a, b| {
+            b.activity
+                .partial_cmp(&a.activity)
+                .unwrap_or(std::cmp::Ordering::Equal)
+                .then(b.cd.partial_cmp(&a.cd).unwrap_or(std::cmp::Ordering::Equal))
+        });
+        let go_threshold = self.config.select_go_threshold;
+        let go: Vec<usize> = (0..candidates.len())
+            .filter(|&i| candidates[i].activity >= go_threshold)
+            .collect();
+
+        let mut probabilities: Vec<Option<f32>> = vec![None; candidates.len()];
+        let (winner, mode) = if candidates.is_empty() {
+            (None, "no_candidates")
+        } else if epsilon > 0.0 && self.next_random() < epsilon {
+            let pick = ((self.next_random() * candidates.len() as f32) as usize)
+                .min(candidates.len() - 1);
+            (Some(pick), "explore")
+        } else if go.is_empty() {
+            (None, "no_go")
+        } else if temperature > 0.0 {
+            let top = candidates[go[0]].activity;
+            let weights: Vec<f32> = go
+                .iter()
+                .map(|&i| ((candidates[i].activity - top) / temperature).exp())
+                .collect();
+            let total: f32 = weights.iter().sum();
+            for (&i, w) in go.iter().zip(&weights) {
+                probabilities[i] = Some(w / total);
+            }
+            let mut target = self.next_random() * total;
+            let mut pick = *go.last().unwrap();
+            for (&i, w) in go.iter().zip(&weights) {
+                if target < *w {
+                    pick = i;
+                    break;
+                }
+                target -= w;
+            }
+            (Some(pick), "softmax")
+        } else {
+            (Some(go[0]), "greedy")
+        };
+
+        if let Some(i) = winner {
+            self.td.pending.insert(candidates[i].action);
+        }
+
+        Python::with_gil(|py| {
+            let out = PyDict::new_bound(py);
+            out.set_item("ok", true)?;
+            out.set_item("winner", winner.map(|i| candidates[i].action))?;
+            out.set_item("winner_stimulus", winner.map(|i| candidates[i].stimulus))?;
+            out.set_item("winner_cd", winner.map(|i| candidates[i].cd))?;
+            out.set_item("mode", mode)?;
+            out.set_item("iterations", iterations)?;
+            out.set_item("converged", converged)?;
+            out.set_item("go_count", go.len())?;
+            out.set_item("temperature", temperature)?;
+            out.set_item("epsilon", epsilon)?;
+
+            let mut rows: Vec<PyObject> = Vec::with_capacity(candidates.len());
+            for (i, c) in candidates.iter().enumerate() {
+                let row = PyDict::new_bound(py);
+                row.set_item("action", c.action)?;
+                row.set_item("stimulus", c.stimulus)?;
+                row.set_item("cd", c.cd)?;
+                row.set_item("drive", c.drive)?;
+                row.set_item("activity", c.activity)?;
+                row.set_item("suppression", c.drive.max(0.0) - c.activity)?;
+                row.set_item("go", c.activity >= go_threshold)?;
+                row.set_item("probability", probabilities[i])?;
+                row.set_item("winner", winner == Some(i))?;
+                rows.push(row.to_object(py));
+            }
+            out.set_item("candidates", rows)?;
+            Ok(out.to_object(py))
+        })
+    }
+
+    /// `apply_reward` on whichever working state (and so eligibility) is swapped in.
+    fn apply_reward_internal(
+        &mut self,
+        reward: f32,
+        nodes: Option<Vec<u64>>,
+        terminal: Option<bool>,
+    ) -> PyResult<PyObject> {
+        if !reward.is_finite() {
+            return Err(PyValueError::new_err(format!("reward must be finite, got {}", reward)));
+        }
+        let mut features: Vec<u64> = match nodes {
+            Some(list) => {
+                for node in &list {
+                    self.strict_check_node(*node, "apply_reward(nodes)")?;
+                }
+                list
+            }
+            None => self
+                .td
+                .pending
+                .iter()
+                .copied()
+                .filter(|n| self.node_index.contains_key(n))
+                .collect(),
+        };
+        features.sort_unstable();
+        features.dedup();
+        self.td.pending.clear();
+
+        let value_now = self.state_value(&features);
+        let mut rpe: Option<f32> = None;
+        let mut updated = 0;
+        if let Some((prev_features, prev_reward)) = self.td.prev.take() {
+            let delta =
+                prev_reward + self.config.td_gamma * value_now - self.state_value(&prev_features);
+            updated += self.td_update(&prev_features, delta);
+            rpe = Some(delta);
+        }
+        let terminal = terminal.unwrap_or(false);
+        if terminal {
+            let delta = reward - self.state_value(&features);
+            updated += self.td_update(&features, delta);
+            rpe = Some(delta);
+            self.td.traces.clear();
+        } else {
+            self.td.prev = Some((features.clone(), reward));
+        }
+        if rpe.is_some() {
+            self.td.last_rpe = rpe;
+            self.values_dirty = true;
+        }
+
+        Python::with_gil(|py| {
+            let out = PyDict::new_bound(py);
+            out.set_item("ok", true)?;
+            out.set_item("rpe", rpe)?;
+            out.set_item("state_value", value_now)?;
+            out.set_item("features", features)?;
+            out.set_item("updated", updated)?;
+            out.set_item("traces", self.td.traces.len())?;
+            out.set_item("terminal", terminal)?;
+            Ok(out.to_object(py))
+        })
+    }
+
+    /// `cd_breakdown` over `peek_typed_connections`: no access counts, no cache fills.
+    fn peek_cd_breakdown(
+        &self,
+        stimulus: u64,
+        context: &[u64],
+        label: &str,
+    ) -> PyResult<Vec<CdBreakdown>> {
+        self.check_cd_inputs(stimulus, context, label)?;
+        let drive_nodes: Vec<u64> = self.drives.values().map(|d| d.node).collect();
+        let conns = gather_cd_connections(stimulus, context, &drive_nodes, |node| {
+            self.peek_typed_connections(node)
+        });
+        Ok(self.score_cd(stimulus, context, &conns))
+    }
+
+    /// Pure Cd scoring over connection lists already fetched by
+    /// `gather_cd_connections`; shared by `cd_breakdown` and the planner's snapshot.
+    fn score_cd(
+        &self,
+        stimulus: u64,
+        context: &[u64],
+        conns: &HashMap<u64, TypedConnections>,
+    ) -> Vec<CdBreakdown> {
+        let edges_of = |node: u64, kind: SynapseKind| -> Vec<(u64, f32)> {
+            conns.get(&node).map_or_else(Vec::new, |edges| {
+                edges.iter().filter(|(_, _, k)| *k == kind).map(|(r, w, _)| (*r, *w)).collect()
+            })
+        };
+        let masking = self.action_mask_active();
+        let gains = self.drive_gains(conns);
+        let min_cost = self.config.cd_min_cost as f64;
+
+        let actions = edges_of(stimulus, SynapseKind::Association);
+        let mut rows: Vec<CdBreakdown> = Vec::with_capacity(actions.len());
+        for (action_id, value) in actions {
+            let cost_conns = edges_of(action_id, SynapseKind::Cost);
+            let cost = if cost_conns.is_empty() {
+                DEFAULT_CD_COST
+            } else {
+                let total: f32 = cost_conns.iter().map(|(_, w)| w.abs()).sum();
+                (total / cost_conns.len() as f32) as f64
+            };
+
+            let mut context_edges: Vec<(u64, f32)> = Vec::new();
+            for ctx in context {
+                for (target, w) in edges_of(*ctx, SynapseKind::Opportunity) {
+                    if target == action_id {
+                        context_edges.push((*ctx, w));
+                    }
+                }
+            }
+
+            let opportunity = if context_edges.is_empty() {
+                DEFAULT_CD_OPPORTUNITY
+            } else {
+                context_edges.iter().map(|(_, w)| *w as f64).sum::<f64>()
+                    / context_edges.len() as f64
+            };
+
+            let (drive_gain, drive) = match gains.get(&action_id) {
+                Some((gain, name)) => (*gain, Some(name.clone())),
+                None => (1.0, None),
+            };
+            let learned_value = self.node_value(action_id);
+            let raw_value = match self.config.cd_value_source.as_str() {
+                "td" => learned_value,
+                "blend" => 0.5 * (value + learned_value),
+                _ => value,
+            };
+            let value = raw_value * drive_gain;
+            let cd = Self::signed_cd(value as f64, opportunity, cost, min_cost);
+            rows.push(CdBreakdown {
+                action: action_id,
+                value,
+                raw_value,
+                drive_gain,
+                drive,
+                learned_value,
+                cost,
+                cost_default: cost_conns.is_empty(),
+                cost_edges: cost_conns.len(),
+                opportunity,
+                opportunity_default: context_edges.is_empty(),
+                context_edges,
+                cd,
+                excluded: None,
+            });
+        }
+
+        rows.sort_by(|a, b| b.cd.partial_cmp(&a.cd).unwrap_or(std::cmp::Ordering::Equal));
+        if masking {
+            for row in rows.iter_mut() {
+                let role = self.role_of(row.action);
+                if role != NodeRole::Action {
+                    row.excluded = Some(format!("role is {}, not action", role.name()));
+                }
             }
         }
+        rows
+    }
+
+    /// `value * opportunity / cost`, except that two negatives never multiply into a
+    /// positive score: inhibitory value or opportunity always ranks the action down.
+    /// Cost is floored at `min_cost` (`cd_min_cost`), so a free harmful action still
+    /// ranks last.
+    fn signed_cd(value: f64, opportunity: f64, cost: f64, min_cost: f64) -> f64 {
+        let magnitude = (value * opportunity).abs() / cost.max(min_cost);
+        if value < 0.0 || opportunity < 0.0 {
+            -magnitude
+        } else {
+            magnitude
+        }
     }
 
-    fn maybe_migrate_legacy_base_to_chunks(&mut self) {
-        if self.node_index.is_empty() || self.has_chunk_files() {
-            return;
+    /// Squashes Cd into (-1, 1) for the action competition. Unlike a clamp it stays
+    /// monotonic, so two strong actions keep their order instead of tying at 1.
+    fn cd_drive(cd: f64) -> f64 {
+        cd.tanh()
+    }
+
+    /// Whether an action->`node` edge predicts a next state for `plan`: sensor and
+    /// homeostatic nodes always do, unassigned nodes only while masking is off.
+    fn is_plan_state(&self, node: u64, masking: bool) -> bool {
+        match self.role_of(node) {
+            NodeRole::Sensor | NodeRole::Homeostatic => true,
+            NodeRole::Unassigned => !masking,
+            _ => false,
         }
+    }
 
-        let mut has_legacy_offsets = false;
-        for meta in self.node_index.values() {
-            if meta.synapse_count > 0
-                && meta.synapse_offset != u64::MAX
-                && !Self::is_chunk_offset(meta.synapse_offset)
-            {
-                has_legacy_offsets = true;
+    /// Beam search over action sequences from `stimulus`. Each step scores the
+    /// current state with `score_cd`; an action's positive `transition` edges to
+    /// state nodes are its predicted outcomes, weighted by the edge (clamped to 1).
+    /// Reads go through `peek_typed_connections` into a local snapshot, so the search
+    /// leaves the cache, access counts and activation untouched. Returns finished
+    /// plans best first, the number of states expanded and the snapshot size.
+    fn plan_search(
+        &self,
+        stimulus: u64,
+        context: &[u64],
+        horizon: u32,
+        beam_width: usize,
+    ) -> (Vec<PlanBranch>, usize, usize) {
+        let gamma = self.config.td_gamma as f64;
+        let masking = self.action_mask_active();
+        let drive_nodes: Vec<u64> = self.drives.values().map(|d| d.node).collect();
+        let mut snapshot: HashMap<u64, TypedConnections> = HashMap::new();
+        let by_value = |a: &PlanBranch, b: &PlanBranch| {
+            b.expected_value.partial_cmp(&a.expected_value).unwrap_or(std::cmp::Ordering::Equal)
+        };
+
+        let mut frontier = vec![PlanBranch {
+            actions: Vec::new(),
+            states: vec![stimulus],
+            probability: 1.0,
+            expected_value: 0.0,
+        }];
+        let mut finished: Vec<PlanBranch> = Vec::new();
+        let mut expanded = 0_usize;
+        for step in 0..horizon {
+            let discount = gamma.powi(step as i32);
+            let mut next: Vec<PlanBranch> = Vec::new();
+            for branch in &frontier {
+                let state = *branch.states.last().unwrap();
+                let conns = gather_cd_connections(state, context, &drive_nodes, |node| {
+                    let cached = snapshot.entry(node);
+                    cached.or_insert_with(|| self.peek_typed_connections(node)).clone()
+                });
+                expanded += 1;
+                let rows = self.score_cd(state, context, &conns);
+                let mut grew = false;
+                for row in rows.iter().filter(|r| r.excluded.is_none()).take(beam_width) {
+                    let gain = discount * branch.probability as f64 * Self::cd_drive(row.cd);
+                    let mut actions = branch.actions.clone();
+                    actions.push(row.action);
+                    let mut targets: HashSet<u64> = HashSet::new();
+                    let transitions: Vec<(u64, f32)> = conns
+                        .get(&row.action)
+                        .map(|edges| {
+                            edges
+                                .iter()
+                                .filter(|(r, w, k)| {
+                                    *k == SynapseKind::Transition
+                                        && *w > 0.0
+                                        && *r != row.action
+                                        && !branch.states.contains(r)
+                                        && self.is_plan_state(*r, masking)
+                                        && targets.insert(*r)
+                                })
+                                .map(|(r, w, _)| (*r, *w))
+                                .collect()
+                        })
+                        .unwrap_or_default();
+                    grew = true;
+                    if transitions.is_empty() {
+                        finished.push(PlanBranch {
+                            actions,
+                            states: branch.states.clone(),
+                            probability: branch.probability,
+                            expected_value: branch.expected_value + gain,
+                        });
+                        continue;
+                    }
+                    for (target, weight) in transitions {
+                        let mut states = branch.states.clone();
+                        states.push(target);
+                        next.push(PlanBranch {
+                            actions: actions.clone(),
+                            states,
+                            probability: branch.probability * weight.min(1.0),
+                            expected_value: branch.expected_value + gain,
+                        });
+                    }
+                }
+                if !grew && !branch.actions.is_empty() {
+                    finished.push(branch.clone());
+                }
+            }
+            next.sort_by(by_value);
+            next.truncate(beam_width);
+            frontier = next;
+            if frontier.is_empty() {
                 break;
             }
         }
-        if !has_legacy_offsets {
-            return;
+        finished.append(&mut frontier);
+        finished.sort_by(by_value);
+        finished.truncate(beam_width);
+        (finished, expanded, snapshot.len())
+    }
+
+    /// Role and threshold for new learned nodes (defaults: unassigned, `default_threshold`).
+    fn parse_node_spec(
+        &self,
+        role: Option<String>,
+        threshold: Option<f32>,
+        label: &str,
+    ) -> PyResult<(NodeRole, f32)> {
+        let role = match role {
+            Some(name) => NodeRole::parse(&name).map_err(PyValueError::new_err)?,
+            None => NodeRole::Unassigned,
+        };
+        let threshold = threshold.unwrap_or(self.config.default_threshold);
+        if !threshold.is_finite() || !(0.0..=1.0).contains(&threshold) {
+            return Err(PyValueError::new_err(format!(
+                "{}: threshold must be in [0, 1], got {}",
+                label, threshold
+            )));
         }
+        Ok((role, threshold))
+    }
 
-        let mut node_ids: Vec<u64> = self.node_index.keys().copied().collect();
-        node_ids.sort_unstable();
-        let mut all_data: Vec<(u64, Vec<Synapse>)> = Vec::with_capacity(node_ids.len());
-        for node_id in node_ids {
-            all_data.push((node_id, self.load_from_base(node_id)));
+    fn strict_check_node(&self, node_id: u64, role: &str) -> PyResult<()> {
+        if self.node_index.contains_key(&node_id) {
+            Ok(())
+        } else {
+            Err(PyValueError::new_err(format!(
+                "Unknown node for {}: {}. Node must be registered in innate registry.",
+                role, node_id
+            )))
         }
-        self.write_base_manifest_and_chunks(&all_data);
-        println!("[Migrasi] base.bin lama dimigrasikan ke chunk range");
     }
-    fn rebuild_base_bin(&mut self) {
-        let node_ids: Vec<u64> = self.node_index.keys().copied().collect();
-        let mut all_data: Vec<(u64, Vec<Synapse>)> = Vec::new();
 
-        for node_id in &node_ids {
-            let mut merged = self.load_from_base(*node_id);
-            if let Some(delta) = self.delta_index.get(node_id) {
-                for (receiver, (weight, _)) in delta {
-                    if let Some(existing) = merged.iter_mut().find(|s| s.receiver_id == *receiver) {
-                        existing.weight = *weight;
-                    } else {
-                        merged.push(Synapse {
-                            receiver_id: *receiver,
-                            weight: *weight,
-                        });
+    /// With `strict_roles` on, rejects nodes whose role is not in `allowed`.
+    fn strict_check_role(&self, node_id: u64, allowed: &[NodeRole], label: &str) -> PyResult<()> {
+        if !self.config.strict_roles {
+            return Ok(());
+        }
+        let role = self.role_of(node_id);
+        if allowed.contains(&role) {
+            return Ok(());
+        }
+        let expected: Vec<&str> = allowed.iter().map(|r| r.name()).collect();
+        Err(PyValueError::new_err(format!(
+            "Role violation for {}: node {} is {}, expected {}.",
+            label,
+            node_id,
+            role.name(),
+            expected.join("|")
+        )))
+    }
+
+    fn role_of(&self, node_id: u64) -> NodeRole {
+        self.node_index.get(&node_id).map_or(NodeRole::Unassigned, |m| m.role)
+    }
+
+    /// Action masking is active once at least one node carries the action role.
+    fn action_mask_active(&self) -> bool {
+        self.config.action_masking && self.node_index.values().any(|m| m.role == NodeRole::Action)
+    }
+
+    /// Validates and applies `roles`, then persists them into the manifest.
+    fn assign_roles(&mut self, roles: HashMap<u64, String>, label: &str) -> PyResult<usize> {
+        let mut parsed: Vec<(u64, NodeRole)> = Vec::with_capacity(roles.len());
+        for (node, name) in roles {
+            self.strict_check_node(node, label)?;
+            parsed.push((node, NodeRole::parse(&name).map_err(PyValueError::new_err)?));
+        }
+        let mut updated = 0;
+        for (node, role) in parsed {
+            if let Some(meta) = self.node_index.get_mut(&node) {
+                if meta.role != role {
+                    meta.role = role;
+                    updated += 1;
+                    // Not in the manifest yet: the role lives in the node's delta record.
+                    if self.pending_nodes.contains(&node) {
+                        let threshold = meta.threshold;
+                        self.append_node_record(node, Some((role, threshold)));
                     }
                 }
             }
+        }
+        if updated > 0 {
+            self.persist_node_roles();
+        }
+        Ok(updated)
+    }
 
-            if !merged.is_empty() {
-                let avg = merged.iter().map(|s| s.weight).sum::<f32>() / merged.len() as f32;
-                let threshold = avg * PRUNE_RATIO;
-                merged.retain(|s| s.weight >= threshold);
-                merged.sort_by(|a, b| b.weight.partial_cmp(&a.weight).unwrap_or(std::cmp::Ordering::Equal));
+    /// Rewrites the role column of the manifest in place (records are fixed size).
+    fn persist_node_roles(&self) {
+        if !self.persists() {
+            return;
+        }
+        let _ = self.storage.patch(BASE_FILE_NAME, |f| {
+            let mut header = [0_u8; BASE_HEADER_SIZE_V2 as usize];
+            f.read_exact(&mut header)?;
+            let version = u16::from_le_bytes(header[4..6].try_into().unwrap());
+            let header_size = if version == BASE_VERSION {
+                BASE_HEADER_SIZE
+            } else {
+                BASE_HEADER_SIZE_V2
+            };
+            let node_count = u32::from_le_bytes(header[6..10].try_into().unwrap()) as u64;
+            for i in 0..node_count {
+                let record_start = header_size + i * NODE_INDEX_SIZE;
+                let mut id_buf = [0_u8; 8];
+                f.seek(SeekFrom::Start(record_start))?;
+                f.read_exact(&mut id_buf)?;
+                let role = self.role_of(u64::from_le_bytes(id_buf)).code();
+                f.seek(SeekFrom::Start(record_start + 28))?;
+                f.write_all(&role.to_le_bytes())?;
             }
+            Ok(())
+        });
+    }
 
-            all_data.push((*node_id, merged));
+    fn get_connections_internal(&mut self, sender: u64) -> Vec<(u64, f32)> {
+        self.get_typed_connections_internal(sender)
+            .into_iter()
+            .map(|(receiver, weight, _)| (receiver, weight))
+            .collect()
+    }
+
+    fn get_typed_connections_internal(&mut self, sender: u64) -> TypedConnections {
+        if !self.node_index.contains_key(&sender) {
+            return Vec::new();
         }
 
-        all_data.sort_by_key(|(node_id, _)| *node_id);
-        self.write_base_manifest_and_chunks(&all_data);
+        self.record_access(sender);
+        let base_synapses = self.get_cached_or_load_base(sender);
+        self.merge_delta(sender, base_synapses)
     }
 
-    fn migrate_innate_registry(&mut self, node_ids: Vec<u64>) -> (u32, u32) {
-        let mut sorted_ids = node_ids;
-        sorted_ids.sort_unstable();
-        sorted_ids.dedup();
-        if sorted_ids.is_empty() {
-            return (0, 0);
+    /// Base synapses of `sender` with its delta entries layered on top.
+    fn merge_delta(&self, sender: u64, base_synapses: Vec<Synapse>) -> TypedConnections {
+        let mut merged: HashMap<u64, (f32, SynapseKind)> = HashMap::new();
+        for s in base_synapses {
+            merged.insert(s.receiver_id, (s.weight, s.kind));
         }
+        if let Some(delta) = self.delta_index.get(&sender) {
+            for (receiver, edge) in delta {
+                if edge.removed {
+                    merged.remove(receiver);
+                } else {
+                    merged.insert(*receiver, (edge.weight, edge.kind));
+                }
+            }
+        }
+        merged.into_iter().map(|(r, (w, k))| (r, w, k)).collect()
+    }
 
-        if self.node_index.is_empty() {
-            self.init_node_pool(sorted_ids);
-            self.loaded_registry_version = self.registry_version;
-            return (0, 0);
+    /// Read-only twin of `get_typed_connections_internal`: base synapses come from
+    /// the pinned map, an LRU `peek` (no recency bump) or straight from disk, with
+    /// delta merged on top. Never records access and never fills a cache.
+    fn peek_typed_connections(&self, sender: u64) -> TypedConnections {
+        let Some(meta) = self.node_index.get(&sender) else {
+            return Vec::new();
+        };
+        let base_synapses = match self.pinned_cache.get(&sender) {
+            Some(v) => v.clone(),
+            None => match self.base_cache.peek(&sender) {
+                Some(v) => v.clone(),
+                None => self.read_synapses_at(meta.synapse_offset, meta.synapse_count),
+            },
+        };
+        self.merge_delta(sender, base_synapses)
+    }
+
+    /// Current (weight, kind) of one edge, delta first; `None` once evicted.
+    fn current_edge_for(&mut self, sender: u64, receiver: u64) -> Option<(f32, SynapseKind)> {
+        if let Some(edge) = self.delta_index.get(&sender).and_then(|m| m.get(&receiver)) {
+            return (!edge.removed).then_some((edge.weight, edge.kind));
         }
+        self.get_cached_or_load_base(sender)
+            .iter()
+            .find(|s| s.receiver_id == receiver)
+            .map(|s| (s.weight, s.kind))
+    }
 
-        let target_set: HashSet<u64> = sorted_ids.iter().copied().collect();
-        let old_ids: Vec<u64> = self.node_index.keys().copied().collect();
-        let old_set: HashSet<u64> = old_ids.iter().copied().collect();
+    fn write_delta_weight(
+        &mut self,
+        sender: u64,
+        receiver: u64,
+        weight: f32,
+        ts: u32,
+        kind: SynapseKind,
+    ) {
+        self.write_delta_entry(DeltaEntry {
+            sender_id: sender,
+            receiver_id: receiver,
+            weight,
+            timestamp: ts,
+            kind,
+            tombstone: false,
+        });
+    }
 
-        let mut old_data: HashMap<u64, Vec<Synapse>> = HashMap::new();
-        for sender in &old_ids {
-            let mut merged = self.load_from_base(*sender);
-            if let Some(delta) = self.delta_index.get(sender) {
-                for (receiver, (weight, _)) in delta {
-                    if let Some(existing) = merged.iter_mut().find(|s| s.receiver_id == *receiver) {
-                        existing.weight = *weight;
-                    } else {
-                        merged.push(Synapse {
-                            receiver_id: *receiver,
-                            weight: *weight,
-                        });
-                    }
+    fn write_delta_entry(&mut self, entry: DeltaEntry) {
+        self.append_delta_entry(&entry);
+        let edges = self.delta_index.entry(entry.sender_id).or_default();
+        DeltaEdge::record(edges, entry.receiver_id, &entry);
+        self.invalidate_sender_cache(entry.sender_id);
+    }
+
+    fn form_synapses_hebbian(&mut self) -> u32 {
+        let nodes: Vec<(u64, f32)> = self
+            .temporal_window
+            .iter()
+            .map(|(node_id, strength, _)| (*node_id, *strength))
+            .collect();
+
+        let mut formed = 0_u32;
+        let mut rooms: HashMap<u64, EdgeRoom> = HashMap::new();
+        for (i, &(sender, s_strength)) in nodes.iter().enumerate() {
+            if !self.node_index.contains_key(&sender) {
+                continue;
+            }
+
+            let sender_thr = self
+                .node_index
+                .get(&sender)
+                .map_or(self.config.default_threshold, |m| m.threshold);
+            if s_strength < sender_thr {
+                continue;
+            }
+
+            for (j, &(receiver, r_strength)) in nodes.iter().enumerate() {
+                if i == j {
+                    continue;
+                }
+                if !self.node_index.contains_key(&receiver) {
+                    continue;
+                }
+
+                let prob = s_strength * r_strength;
+                if rand_f32() > prob {
+                    continue;
+                }
+
+                if self.current_edge_for(sender, receiver).is_some() {
+                    continue;
                 }
+                if !self.make_room_for(sender, receiver, &mut rooms) {
+                    break;
+                }
+
+                let ts = self.tick;
+                let initial_weight = self.config.initial_weight;
+                let kind = SynapseKind::Association;
+                self.write_delta_weight(sender, receiver, initial_weight, ts, kind);
+                formed = formed.saturating_add(1);
             }
-            old_data.insert(*sender, merged);
         }
 
-        self.node_index.clear();
-        for id in &sorted_ids {
-            self.node_index.insert(
-                *id,
-                NodeMeta {
-                    node_id: *id,
-                    synapse_count: 0,
-                    synapse_offset: u64::MAX,
-                    threshold: DEFAULT_THRESHOLD,
-                    checksum: 0,
-                },
-            );
+        formed
+    }
+
+    /// Pair-based STDP over the temporal window. Window order is causal order:
+    /// an earlier entry is "pre" for every later entry, so pre->post is potentiated
+    /// and the reverse post->pre edge is depressed. Entries from the same
+    /// `spread_activation` call share a tick (dt = 0) and are ordered by the wave.
+    /// Only pairs whose post spike arrived since the previous pass are updated, so
+    /// repeated calls over an unchanged window leave the weights alone.
+    fn form_synapses_stdp(&mut self) -> (u32, u32, u32) {
+        let nodes: Vec<(u64, f32, u32)> = self.temporal_window.iter().copied().collect();
+        let params = self.config.clone();
+        let seen = self.stdp_tick;
+        if let Some(newest) = nodes.iter().map(|n| n.2).max() {
+            self.stdp_tick = Some(seen.map_or(newest, |t| t.max(newest)));
         }
 
-        let mut all_data: Vec<(u64, Vec<Synapse>)> = Vec::with_capacity(sorted_ids.len());
-        for id in &sorted_ids {
-            let mut syns = old_data.remove(id).unwrap_or_default();
-            syns.retain(|s| target_set.contains(&s.receiver_id));
-            syns.sort_by(|a, b| b.weight.partial_cmp(&a.weight).unwrap_or(std::cmp::Ordering::Equal));
-            all_data.push((*id, syns));
+        let mut changes: HashMap<(u64, u64), f32> = HashMap::new();
+        for (i, &(pre, pre_strength, pre_tick)) in nodes.iter().enumerate() {
+            if !self.node_index.contains_key(&pre) {
+                continue;
+            }
+            let pre_thr = self
+                .node_index
+                .get(&pre)
+                .map_or(self.config.default_threshold, |m| m.threshold);
+            if pre_strength < pre_thr {
+                continue;
+            }
+
+            for &(post, _, post_tick) in nodes.iter().skip(i + 1) {
+                if post == pre || !self.node_index.contains_key(&post) {
+                    continue;
+                }
+                if seen.is_some_and(|t| post_tick <= t) {
+                    continue;
+                }
+                let dt = post_tick.saturating_sub(pre_tick) as f32;
+                let ltp = params.stdp_a_plus * (-dt / params.stdp_tau_plus).exp();
+                let ltd = params.stdp_a_minus * (-dt / params.stdp_tau_minus).exp();
+                *changes.entry((pre, post)).or_insert(0.0) += ltp;
+                *changes.entry((post, pre)).or_insert(0.0) -= ltd;
+            }
         }
-        self.write_base_manifest_and_chunks(&all_data);
 
-        let removed_nodes = old_set.difference(&target_set).count() as u32;
-        let added_nodes = target_set.difference(&old_set).count() as u32;
+        let mut keys: Vec<(u64, u64)> = changes.keys().copied().collect();
+        keys.sort_unstable();
 
-        self.delta_index.clear();
-        self.activation.clear();
-        self.temporal_window.clear();
-        self.base_cache.clear();
-        self.pinned_cache.clear();
-        self.pinned_set.clear();
-        self.access_count.clear();
-        self.access_since_recompute = 0;
-        self.reset_delta_file();
-        self.loaded_registry_version = self.registry_version;
-        self.refresh_cache_budget();
-        self.recompute_pinned_set(true);
+        let (mut potentiated, mut depressed, mut created) = (0_u32, 0_u32, 0_u32);
+        let mut rooms: HashMap<u64, EdgeRoom> = HashMap::new();
+        let ts = self.tick;
+        for (sender, receiver) in keys {
+            let dw = changes.get(&(sender, receiver)).copied().unwrap_or(0.0);
+            let edge = self.current_edge_for(sender, receiver);
+            // Timing says nothing about cost, opportunity or transitions; retuning
+            // those edges would silently move Cd terms.
+            if edge.is_some_and(|(_, k)| k != SynapseKind::Association) {
+                continue;
+            }
+            let old = edge.map(|(w, _)| w);
+            let kind = SynapseKind::Association;
+            // Sign is preserved: LTP grows |w| and LTD shrinks it towards zero,
+            // so an inhibitory edge never flips into an excitatory one.
+            let new_weight = match old {
+                Some(w) if w < 0.0 => (w - dw).clamp(-1.0, 0.0),
+                Some(w) => (w + dw).clamp(0.0, 1.0),
+                None => {
+                    if dw <= 0.0 || !self.make_room_for(sender, receiver, &mut rooms) {
+                        continue;
+                    }
+                    dw.clamp(0.0, 1.0)
+                }
+            };
+            if old.is_some_and(|w| (w - new_weight).abs() <= f32::EPSILON) {
+                continue;
+            }
 
-        (added_nodes, removed_nodes)
+            self.write_delta_weight(sender, receiver, new_weight, ts, kind);
+            match old {
+                None => created = created.saturating_add(1),
+                Some(_) if dw > 0.0 => potentiated = potentiated.saturating_add(1),
+                Some(_) => depressed = depressed.saturating_add(1),
+            }
+        }
+
+        (potentiated, depressed, created)
     }
 
-    fn ensure_innate_registry_internal(&mut self, node_ids: Vec<u64>) -> (bool, u32, u32) {
-        let mut sorted_ids = node_ids;
-        sorted_ids.sort_unstable();
-        sorted_ids.dedup();
-        if sorted_ids.is_empty() {
-            return (false, 0, 0);
+    /// Routes one stimulus to its owner shard. Stimuli given the same
+    /// `origin_tick` are integrated as one multi-seed wave.
+    fn submit_stimulus_internal(
+        &mut self,
+        node_id: u64,
+        strength: f32,
+        source: Option<String>,
+        origin_tick: Option<u64>,
+    ) -> PyResult<bool> {
+        self.strict_check_node(node_id, "submit_stimulus(node_id)")?;
+        self.refresh_async_guard_mode();
+        let Some(runtime) = self.async_runtime.as_ref() else {
+            return Err(PyValueError::new_err(
+                "async runtime is OFF; call start_async_runtime first",
+            ));
+        };
+        let owner = self.owner_shard(node_id);
+
+        let ingress_ok = runtime.rt.block_on(async {
+            let mut s = runtime.shared.lock().await;
+            s.guard_mode = self.async_state.guard_mode.clone();
+            if s.ingress_paused {
+                s.dropped_total = s.dropped_total.saturating_add(1);
+                return false;
+            }
+            if s.guard_mode == "critical" && s.global_queue_len > 20_000 {
+                s.dropped_total = s.dropped_total.saturating_add(1);
+                return false;
+            }
+            s.global_queue_len = s.global_queue_len.saturating_add(1);
+            if let Some(slot) = s.per_shard_queue_len.get_mut(owner) {
+                *slot = slot.saturating_add(1);
+            }
+            true
+        });
+        if !ingress_ok {
+            self.sync_async_state_from_shared();
+            return Ok(false);
         }
 
-        let mut current_ids: Vec<u64> = self.node_index.keys().copied().collect();
-        current_ids.sort_unstable();
-        let needs_migrate =
-            self.node_index.is_empty()
-                || self.loaded_registry_version != self.registry_version
-                || current_ids != sorted_ids;
-        if !needs_migrate {
-            return (false, 0, 0);
+        let (tx, rx) = oneshot::channel();
+        let strength = self.drive_scaled_strength(node_id, strength);
+        self.td.pending.insert(node_id);
+        let cmd = ShardCommand::Stimulus {
+            node_id,
+            strength: strength.clamp(0.0, 1.0),
+            source: source.unwrap_or_else(|| "unknown".to_string()),
+            origin_tick: origin_tick
+                .unwrap_or_else(|| runtime.global_tick.fetch_add(1, Ordering::SeqCst)),
+            reply: tx,
+        };
+        if runtime.shard_txs[owner].send(cmd).is_err() {
+            return Err(PyValueError::new_err("failed to route stimulus to owner shard"));
         }
 
-        let (added, removed) = self.migrate_innate_registry(sorted_ids);
-        (true, added, removed)
+        let accepted = runtime.rt.block_on(async { rx.await.unwrap_or(false) });
+        self.sync_async_state_from_shared();
+        Ok(accepted)
     }
 
-    fn strict_check_node(&self, node_id: u64, role: &str) -> PyResult<()> {
-        if self.node_index.contains_key(&node_id) {
-            Ok(())
-        } else {
-            Err(PyValueError::new_err(format!(
-                "Unknown node for {}: {}. Node must be registered in innate registry.",
-                role, node_id
-            )))
+    fn reset_delta_file(&self) {
+        if !self.persists() {
+            return;
         }
+        self.storage
+            .write(DELTA_FILE_NAME, &self.delta_header())
+            .expect("Gagal reset delta.bin");
     }
 
-    fn get_connections_internal(&mut self, sender: u64) -> Vec<(u64, f32)> {
-        if !self.node_index.contains_key(&sender) {
-            return Vec::new();
+    /// Node values, drives and config keys the fork changed since it was taken.
+    /// Fails, listing every conflict, when this engine changed one of them to a
+    /// different value in the meantime.
+    fn fork_changes(&self, fork: &RagpEngine) -> PyResult<ForkChanges> {
+        let Some(snap) = fork.fork.as_ref() else {
+            return Ok(ForkChanges::default());
+        };
+        let mut changes = ForkChanges::default();
+        let mut conflicts: Vec<String> = Vec::new();
+
+        let mut nodes: Vec<u64> = fork.node_index.keys().copied().collect();
+        nodes.sort_unstable();
+        for node in nodes {
+            let base = snap.values.get(&node).copied().unwrap_or(0.0);
+            let theirs = fork.node_value(node);
+            let ours = self.node_value(node);
+            if theirs == base {
+                continue;
+            }
+            if ours != base && ours != theirs {
+                conflicts.push(format!("value of node {}", node));
+            }
+            changes.values.push((node, theirs));
         }
 
-        self.record_access(sender);
-        let base_synapses = self.get_cached_or_load_base(sender);
-
-        let mut merged: HashMap<u64, f32> = HashMap::new();
-        for s in base_synapses {
-            merged.insert(s.receiver_id, s.weight);
+        let mut names: Vec<&String> = snap.drives.keys().chain(fork.drives.keys()).collect();
+        names.sort_unstable();
+        names.dedup();
+        for name in names {
+            let (base, theirs) = (snap.drives.get(name), fork.drives.get(name));
+            if theirs == base {
+                continue;
+            }
+            let ours = self.drives.get(name);
+            if ours != base && ours != theirs {
+                conflicts.push(format!("drive '{}'", name));
+            }
+            changes.drives.push((name.clone(), theirs.cloned()));
         }
-        if let Some(delta) = self.delta_index.get(&sender) {
-            for (receiver, (weight, _)) in delta {
-                merged.insert(*receiver, *weight);
+
+        for key in EngineConfig::KEYS {
+            let (base, theirs) = (snap.config.get(key), fork.config.get(key));
+            if theirs == base {
+                continue;
+            }
+            if self.config.get(key) != base && self.config.get(key) != theirs {
+                conflicts.push(format!("config key '{}'", key));
+            }
+            if let Some(value) = theirs {
+                let value = ConfigValue::from_toml(key, &value).map_err(PyValueError::new_err)?;
+                changes.config.push((key, value));
             }
         }
-        merged.into_iter().collect()
+
+        if !conflicts.is_empty() {
+            return Err(PyValueError::new_err(format!(
+                "commit_fork: fork and parent both changed {}",
+                conflicts.join(", ")
+            )));
+        }
+        Ok(changes)
     }
+}
 
-    fn reset_delta_file(&self) {
-        let mut f = File::create(&self.delta_path).expect("Gagal reset delta.bin");
-        f.write_all(&MAGIC_DELTA.to_le_bytes()).unwrap();
-        f.write_all(&VERSION.to_le_bytes()).unwrap();
-        let reg = self.registry_version.min(u16::MAX as u32) as u16;
-        f.write_all(&reg.to_le_bytes()).unwrap();
+impl Drop for RagpEngine {
+    fn drop(&mut self) {
+        self.flush_deferred();
     }
 }
 
 #[pymethods]
 impl RagpEngine {
     #[new]
-    fn new(storage_dir: String) -> Self {
+    #[pyo3(signature = (storage_dir, config=None))]
+    fn new(storage_dir: String, config: Option<EngineConfig>) -> PyResult<Self> {
         let path = PathBuf::from(&storage_dir);
         if !path.exists() {
             std::fs::create_dir_all(&path).expect("Gagal membuat direktori storage");
         }
-
-        let base_path = path.join("base.bin");
-        let delta_path = path.join("delta.bin");
-        let capacity = NonZeroUsize::new(LRU_CAPACITY).unwrap();
-
-        let mut engine = RagpEngine {
-            storage_dir: path.clone(),
-            base_path,
-            delta_path,
-            node_index: HashMap::new(),
-            delta_index: HashMap::new(),
-            activation: HashMap::new(),
-            temporal_window: VecDeque::new(),
-            tick: 0,
-            base_cache: LruCache::new(capacity),
-            pinned_cache: HashMap::new(),
-            pinned_set: HashSet::new(),
-            access_count: HashMap::new(),
-            access_since_recompute: 0,
-            cache_policy: Self::env_policy("RAGP_CACHE_POLICY", DEFAULT_CACHE_POLICY),
-            cache_ram_fraction: Self::env_f32("RAGP_CACHE_RAM_FRACTION", DEFAULT_CACHE_RAM_FRACTION),
-            cache_ram_min_mb: Self::env_u64("RAGP_CACHE_RAM_MIN_MB", DEFAULT_CACHE_RAM_MIN_MB),
-            cache_ram_max_mb: Self::env_u64("RAGP_CACHE_RAM_MAX_MB", DEFAULT_CACHE_RAM_MAX_MB),
-            cache_pin_fraction: Self::env_f32("RAGP_CACHE_PIN_FRACTION", DEFAULT_CACHE_PIN_FRACTION),
-            cache_budget_bytes: 0,
-            pinned_budget_bytes: 0,
-            lru_budget_bytes: 0,
-            cache_bytes_est: 0,
-            pinned_bytes_est: 0,
-            lru_bytes_est: 0,
-            registry_version: Self::env_u32(
-                "RAGP_INNATE_REGISTRY_VERSION",
-                DEFAULT_INNATE_REGISTRY_VERSION,
-            ),
-            loaded_registry_version: DEFAULT_INNATE_REGISTRY_VERSION,
-            async_state: Self::default_async_state(),
-            async_runtime: None,
-        };
-
-        engine.load_node_index();
-        engine.maybe_migrate_legacy_base_to_chunks();
-        engine.load_node_index();
-        engine.init_delta_if_needed();
-        engine.load_delta_index();
-        engine.refresh_cache_budget();
-        engine.recompute_pinned_set(true);
-        engine
+        Self::open_storage(Storage::Disk(path), config)
     }
-    fn init_node_pool(&mut self, node_ids: Vec<u64>) {
-        self.node_index.clear();
-        self.delta_index.clear();
-        self.activation.clear();
-        self.temporal_window.clear();
-        self.base_cache.clear();
-        self.pinned_cache.clear();
-        self.pinned_set.clear();
-        self.access_count.clear();
-        self.access_since_recompute = 0;
-        self.tick = 0;
-        self.clear_chunk_files();
 
-        let mut sorted_ids = node_ids;
-        sorted_ids.sort_unstable();
-        sorted_ids.dedup();
+    /// Engine whose files live in memory: same formats, consolidation and pruning
+    /// as on disk, but no directory is created and nothing is written until
+    /// `save_to`.
+    #[staticmethod]
+    #[pyo3(signature = (config=None))]
+    fn in_memory(config: Option<EngineConfig>) -> PyResult<Self> {
+        Self::open_storage(Storage::memory(), config)
+    }
 
-        for id in &sorted_ids {
-            self.node_index.insert(
-                *id,
-                NodeMeta {
-                    node_id: *id,
-                    synapse_count: 0,
-                    synapse_offset: u64::MAX,
-                    threshold: DEFAULT_THRESHOLD,
-                    checksum: 0,
-                },
-            );
+    /// In-memory engine started from a copy of the engine files in `dir`; the
+    /// directory is only read.
+    #[staticmethod]
+    #[pyo3(signature = (dir, config=None))]
+    fn load_from(dir: String, config: Option<EngineConfig>) -> PyResult<Self> {
+        let path = PathBuf::from(&dir);
+        if !path.is_dir() {
+            return Err(PyValueError::new_err(format!(
+                "load_from: {} is not a directory",
+                dir
+            )));
         }
+        let source = Storage::Disk(path);
+        let storage = Storage::memory();
+        for name in source.list().into_iter().filter(|n| Self::is_engine_file(n)) {
+            let bytes = source.read(&name).ok_or_else(|| {
+                PyValueError::new_err(format!("load_from: cannot read {}", source.display(&name)))
+            })?;
+            let _ = storage.write(&name, &bytes);
+        }
+        Self::open_storage(storage, config)
+    }
 
-        let all_data: Vec<(u64, Vec<Synapse>)> = sorted_ids
-            .iter()
-            .map(|id| (*id, Vec::new()))
-            .collect();
-        self.write_base_manifest_and_chunks(&all_data);
-
-        self.reset_delta_file();
-        self.refresh_cache_budget();
-        self.recompute_pinned_set(true);
-
-        println!("[RagpEngine] {} node diinisialisasi (tanpa sinapsis)", self.node_index.len());
+    /// Writes a copy of this engine's files into `dir` (created if missing) in the
+    /// layout `RagpEngine(dir)` opens, replacing engine files already there.
+    /// Returns the number of files written.
+    fn save_to(&mut self, dir: String) -> PyResult<usize> {
+        if self.fork.is_some() {
+            return Err(PyValueError::new_err(
+                "save_to: a fork has no files of its own; commit_fork it first",
+            ));
+        }
+        self.flush_deferred();
+        let path = PathBuf::from(&dir);
+        if let Storage::Disk(own) = &self.storage {
+            let same = own.canonicalize().ok().is_some_and(|o| path.canonicalize().ok() == Some(o));
+            if same {
+                return Err(PyValueError::new_err(format!(
+                    "save_to: {} is this engine's own storage directory",
+                    dir
+                )));
+            }
+        }
+        std::fs::create_dir_all(&path)
+            .map_err(|e| PyValueError::new_err(format!("save_to: {}: {}", dir, e)))?;
+
+        let target = Storage::Disk(path);
+        let names: Vec<String> =
+            self.storage.list().into_iter().filter(|n| Self::is_engine_file(n)).collect();
+        for stale in target.list() {
+            if Self::is_engine_file(&stale) && !names.contains(&stale) {
+                target.remove(&stale);
+            }
+        }
+        for name in &names {
+            let bytes = self.storage.read(name).unwrap_or_default();
+            target.write(name, &bytes).map_err(|e| {
+                PyValueError::new_err(format!("save_to: {}: {}", target.display(name), e))
+            })?;
+        }
+        Ok(names.len())
+    }
+    fn init_node_pool(&mut self, node_ids: Vec<u64>) -> PyResult<()> {
+        self.check_base_writable("init_node_pool")?;
+        self.init_node_pool_internal(node_ids);
+        Ok(())
     }
 
-    fn ensure_innate_registry(&mut self, node_ids: Vec<u64>) -> String {
-        let (migrated, added, removed) = self.ensure_innate_registry_internal(node_ids);
-        if migrated {
+    fn ensure_innate_registry(
+        &mut self,
+        node_ids: Vec<u64>,
+        roles: Option<HashMap<u64, String>>,
+    ) -> PyResult<String> {
+        if let Some(id) = node_ids.iter().find(|id| **id >= LEARNED_NODE_ID_START) {
+            return Err(PyValueError::new_err(format!(
+                "ensure_innate_registry: node {} is in the learned range (>= {})",
+                id, LEARNED_NODE_ID_START
+            )));
+        }
+        let (migrated, added, removed) = self.ensure_innate_registry_internal(node_ids)?;
+        let mut out = if migrated {
             format!(
                 "migrated=true registry_version={} added_nodes={} removed_nodes={}",
                 self.registry_version, added, removed
@@ -1254,10 +5400,174 @@ impl RagpEngine {
                 "migrated=false registry_version={} added_nodes=0 removed_nodes=0",
                 self.registry_version
             )
+        };
+        if let Some(roles) = roles {
+            let updated = self.assign_roles(roles, "ensure_innate_registry(roles)")?;
+            out.push_str(&format!(" roles_updated={}", updated));
+        }
+        Ok(out)
+    }
+
+    /// Adds learned nodes (IDs in [2^30, 2^31)) without a registry migration. Each node is
+    /// logged to delta.bin and written to the base at the next consolidation.
+    /// IDs that already exist are skipped.
+    fn add_nodes(
+        &mut self,
+        node_ids: Vec<u64>,
+        role: Option<String>,
+        threshold: Option<f32>,
+    ) -> PyResult<PyObject> {
+        let (role, threshold) = self.parse_node_spec(role, threshold, "add_nodes")?;
+        for id in &node_ids {
+            if !(LEARNED_NODE_ID_START..LEARNED_NODE_ID_END).contains(id) {
+                return Err(PyValueError::new_err(format!(
+                    "add_nodes: node {} is outside the learned range [{}, {}); innate nodes \
+                     come from ensure_innate_registry",
+                    id, LEARNED_NODE_ID_START, LEARNED_NODE_ID_END
+                )));
+            }
+            if self.retired_nodes.contains(id) {
+                return Err(PyValueError::new_err(format!(
+                    "add_nodes: node {} was retired since the last consolidation",
+                    id
+                )));
+            }
+        }
+        let mut added: Vec<u64> = Vec::new();
+        let mut skipped: Vec<u64> = Vec::new();
+        for id in node_ids {
+            if self.node_index.contains_key(&id) || added.contains(&id) {
+                skipped.push(id);
+                continue;
+            }
+            self.append_node_record(id, Some((role, threshold)));
+            self.apply_node_add(id, role, threshold);
+            added.push(id);
+        }
+        if !added.is_empty() {
+            self.refresh_async_graph();
+        }
+
+        Python::with_gil(|py| {
+            let out = PyDict::new_bound(py);
+            out.set_item("ok", true)?;
+            out.set_item("added", added)?;
+            out.set_item("skipped", skipped)?;
+            Ok(out.to_object(py))
+        })
+    }
+
+    /// Hands out `count` fresh learned IDs and adds them like `add_nodes`. A retired
+    /// ID is not handed out again before the next consolidation, so edges left in
+    /// the base for it cannot revive.
+    fn allocate_nodes(
+        &mut self,
+        count: usize,
+        role: Option<String>,
+        threshold: Option<f32>,
+    ) -> PyResult<PyObject> {
+        let (role, threshold) = self.parse_node_spec(role, threshold, "allocate_nodes")?;
+        if LEARNED_NODE_ID_END.saturating_sub(self.next_learned_id) < count as u64 {
+            return Err(PyValueError::new_err(format!(
+                "allocate_nodes: learned ID range exhausted ({} requested, next id {})",
+                count, self.next_learned_id
+            )));
+        }
+        let mut ids: Vec<u64> = Vec::with_capacity(count);
+        for _ in 0..count {
+            let id = self.next_learned_id;
+            self.append_node_record(id, Some((role, threshold)));
+            self.apply_node_add(id, role, threshold);
+            ids.push(id);
+        }
+        if !ids.is_empty() {
+            self.refresh_async_graph();
+        }
+
+        Python::with_gil(|py| {
+            let out = PyDict::new_bound(py);
+            out.set_item("ok", true)?;
+            out.set_item("ids", ids)?;
+            Ok(out.to_object(py))
+        })
+    }
+
+    /// Retires learned nodes: they and their delta edges disappear at once, base
+    /// edges touching them are dropped at the next consolidation. Unknown IDs are
+    /// skipped; innate nodes can only be removed through the registry.
+    fn retire_nodes(&mut self, node_ids: Vec<u64>) -> PyResult<PyObject> {
+        if let Some(id) = node_ids.iter().find(|id| **id < LEARNED_NODE_ID_START) {
+            return Err(PyValueError::new_err(format!(
+                "retire_nodes: node {} is an innate node; change the registry instead",
+                id
+            )));
+        }
+        let mut retired: Vec<u64> = Vec::new();
+        let mut skipped: Vec<u64> = Vec::new();
+        for id in node_ids {
+            if !self.node_index.contains_key(&id) {
+                skipped.push(id);
+                continue;
+            }
+            self.append_node_record(id, None);
+            self.apply_node_retire(id);
+            retired.push(id);
+        }
+        if !retired.is_empty() {
+            self.save_concepts();
+            self.refresh_async_graph();
+        }
+
+        Python::with_gil(|py| {
+            let out = PyDict::new_bound(py);
+            out.set_item("ok", true)?;
+            out.set_item("retired", retired)?;
+            out.set_item("skipped", skipped)?;
+            Ok(out.to_object(py))
+        })
+    }
+
+    /// IDs of all learned nodes, ascending.
+    fn list_learned_nodes(&self) -> Vec<u64> {
+        let mut ids: Vec<u64> = self
+            .node_index
+            .keys()
+            .copied()
+            .filter(|id| *id >= LEARNED_NODE_ID_START)
+            .collect();
+        ids.sort_unstable();
+        ids
+    }
+
+    /// Assigns roles (`sensor`, `context`, `action`, `internal`, `homeostatic`,
+    /// `unassigned`) to registered nodes; persisted in the base manifest.
+    fn set_node_roles(&mut self, roles: HashMap<u64, String>) -> PyResult<PyObject> {
+        let updated = self.assign_roles(roles, "set_node_roles")?;
+        let mut counts: HashMap<&'static str, usize> = HashMap::new();
+        for meta in self.node_index.values() {
+            *counts.entry(meta.role.name()).or_insert(0) += 1;
         }
+
+        Python::with_gil(|py| {
+            let out = PyDict::new_bound(py);
+            out.set_item("ok", true)?;
+            out.set_item("updated", updated)?;
+            out.set_item("counts", counts)?;
+            out.set_item("action_masking", self.action_mask_active())?;
+            Ok(out.to_object(py))
+        })
+    }
+
+    /// Roles of all nodes that have one assigned.
+    fn get_node_roles(&self) -> HashMap<u64, String> {
+        self.node_index
+            .iter()
+            .filter(|(_, m)| m.role != NodeRole::Unassigned)
+            .map(|(id, m)| (*id, m.role.name().to_string()))
+            .collect()
     }
 
-    fn start_async_runtime(&mut self, config: Option<&PyAny>) -> PyResult<String> {
+    fn start_async_runtime(&mut self, config: Option<&Bound<'_, PyAny>>) -> PyResult<String> {
         if let Some(obj) = config {
             if !obj.is_none() {
                 let cfg = obj.downcast::<PyDict>()?;
@@ -1308,6 +5618,15 @@ impl RagpEngine {
             adjacency,
             threshold,
             activation: HashMap::new(),
+            max_spread_depth: self.config.max_spread_depth,
+            default_threshold: self.config.default_threshold,
+            dynamics: self.config.dynamics(),
+            activation_tick: self.tick as u64,
+            last_wave_tick: None,
+            wave_seq: 0,
+            waves: HashMap::new(),
+            trace_enabled: self.config.trace_activation,
+            trace: HashMap::new(),
             ingress_paused: false,
             global_queue_len: 0,
             per_shard_queue_len: vec![0; shard_count],
@@ -1363,78 +5682,33 @@ impl RagpEngine {
         self.async_state.per_shard_processed = vec![0; shard_count];
 
         Ok(format!(
-            "async_on=true shards={} guard_mode={}",
-            shard_count, self.async_state.guard_mode
-        ))
-    }
-
-    fn stop_async_runtime(&mut self) -> String {
-        if let Some(runtime) = self.async_runtime.take() {
-            for tx in &runtime.shard_txs {
-                let _ = tx.send(ShardCommand::Stop);
-            }
-            drop(runtime);
-        }
-        self.async_state.enabled = false;
-        self.async_state.ingress_paused = false;
-        self.async_state.global_queue_len = 0;
-        self.async_state.per_shard_queue_len = vec![0; self.async_state.shard_count];
-        "async_on=false".to_string()
-    }
-
-    fn submit_stimulus(
-        &mut self,
-        node_id: u64,
-        strength: f32,
-        source: Option<String>,
-        _ts_ms: Option<u64>,
-    ) -> PyResult<bool> {
-        self.strict_check_node(node_id, "submit_stimulus(node_id)")?;
-        self.refresh_async_guard_mode();
-        let Some(runtime) = self.async_runtime.as_ref() else {
-            return Err(PyValueError::new_err(
-                "async runtime is OFF; call start_async_runtime first",
-            ));
-        };
-        let owner = self.owner_shard(node_id);
-
-        let ingress_ok = runtime.rt.block_on(async {
-            let mut s = runtime.shared.lock().await;
-            s.guard_mode = self.async_state.guard_mode.clone();
-            if s.ingress_paused {
-                s.dropped_total = s.dropped_total.saturating_add(1);
-                return false;
-            }
-            if s.guard_mode == "critical" && s.global_queue_len > 20_000 {
-                s.dropped_total = s.dropped_total.saturating_add(1);
-                return false;
-            }
-            s.global_queue_len = s.global_queue_len.saturating_add(1);
-            if let Some(slot) = s.per_shard_queue_len.get_mut(owner) {
-                *slot = slot.saturating_add(1);
-            }
-            true
-        });
-        if !ingress_ok {
-            self.sync_async_state_from_shared();
-            return Ok(false);
-        }
+            "async_on=true shards={} guard_mode={}",
+            shard_count, self.async_state.guard_mode
+        ))
+    }
 
-        let (tx, rx) = oneshot::channel();
-        let cmd = ShardCommand::Stimulus {
-            node_id,
-            strength: strength.max(0.0).min(1.0),
-            source: source.unwrap_or_else(|| "unknown".to_string()),
-            origin_tick: runtime.global_tick.fetch_add(1, Ordering::SeqCst),
-            reply: tx,
-        };
-        if runtime.shard_txs[owner].send(cmd).is_err() {
-            return Err(PyValueError::new_err("failed to route stimulus to owner shard"));
+    fn stop_async_runtime(&mut self) -> String {
+        if let Some(runtime) = self.async_runtime.take() {
+            for tx in &runtime.shard_txs {
+                let _ = tx.send(ShardCommand::Stop);
+            }
+            drop(runtime);
         }
+        self.async_state.enabled = false;
+        self.async_state.ingress_paused = false;
+        self.async_state.global_queue_len = 0;
+        self.async_state.per_shard_queue_len = vec![0; self.async_state.shard_count];
+        "async_on=false".to_string()
+    }
 
-        let accepted = runtime.rt.block_on(async { rx.await.unwrap_or(false) });
-        self.sync_async_state_from_shared();
-        Ok(accepted)
+    fn submit_stimulus(
+        &mut self,
+        node_id: u64,
+        strength: f32,
+        source: Option<String>,
+        _ts_ms: Option<u64>,
+    ) -> PyResult<bool> {
+        self.submit_stimulus_internal(node_id, strength, source, None)
     }
 
     fn submit_stimuli(&mut self, batch: Vec<(u64, f32, String)>) -> PyResult<PyObject> {
@@ -1463,8 +5737,13 @@ impl RagpEngine {
         let mut grouped_vec: Vec<((u64, String), f32)> = grouped.into_iter().collect();
         grouped_vec.sort_by_key(|((node_id, _), _)| self.owner_shard(*node_id));
 
+        // One origin tick per batch: the batch spreads as a single multi-seed wave.
+        let batch_tick = self
+            .async_runtime
+            .as_ref()
+            .map(|runtime| runtime.global_tick.fetch_add(1, Ordering::SeqCst));
         for ((node_id, source), strength) in grouped_vec {
-            match self.submit_stimulus(node_id, strength, Some(source), None)? {
+            match self.submit_stimulus_internal(node_id, strength, Some(source), batch_tick)? {
                 true => accepted = accepted.saturating_add(1),
                 false => rejected = rejected.saturating_add(1),
             }
@@ -1547,53 +5826,837 @@ impl RagpEngine {
         })
     }
 
-    fn get_connections(&mut self, sender: u64) -> PyResult<Vec<(u64, f32)>> {
+    /// Merged outgoing edges of `sender`; `kind` keeps only edges of that type.
+    fn get_connections(&mut self, sender: u64, kind: Option<String>) -> PyResult<Vec<(u64, f32)>> {
         self.strict_check_node(sender, "get_connections(sender)")?;
-        Ok(self.get_connections_internal(sender))
+        let Some(kind) = kind else {
+            return Ok(self.get_connections_internal(sender));
+        };
+        let kind = SynapseKind::parse(&kind).map_err(PyValueError::new_err)?;
+        Ok(self
+            .get_typed_connections_internal(sender)
+            .into_iter()
+            .filter(|(_, _, k)| *k == kind)
+            .map(|(receiver, weight, _)| (receiver, weight))
+            .collect())
+    }
+
+    /// Merged outgoing edges of `sender` as (receiver, weight, kind).
+    fn get_typed_connections(&mut self, sender: u64) -> PyResult<Vec<(u64, f32, String)>> {
+        self.strict_check_node(sender, "get_typed_connections(sender)")?;
+        Ok(self
+            .get_typed_connections_internal(sender)
+            .into_iter()
+            .map(|(receiver, weight, kind)| (receiver, weight, kind.name().to_string()))
+            .collect())
+    }
+
+    /// `get_connections` without side effects: no access count, no cache fill or
+    /// recency bump, no pinned-set refresh.
+    fn peek_connections(&self, sender: u64, kind: Option<String>) -> PyResult<Vec<(u64, f32)>> {
+        self.strict_check_node(sender, "peek_connections(sender)")?;
+        let kind = kind.map(|k| SynapseKind::parse(&k)).transpose().map_err(PyValueError::new_err)?;
+        self.count_peek();
+        Ok(self
+            .peek_typed_connections(sender)
+            .into_iter()
+            .filter(|(_, _, k)| kind.is_none_or(|want| *k == want))
+            .map(|(receiver, weight, _)| (receiver, weight))
+            .collect())
+    }
+
+    /// `compute_cd` without side effects (same checks and scoring, peeked reads).
+    fn peek_cd(&self, stimulus: u64, context: Vec<u64>) -> PyResult<Vec<(u64, f64)>> {
+        let rows = self.peek_cd_breakdown(stimulus, &context, "peek_cd")?;
+        self.count_peek();
+        Ok(rows
+            .iter()
+            .filter(|r| r.excluded.is_none())
+            .map(|r| (r.action, r.cd))
+            .collect())
+    }
+
+    /// What one `spread_activation_many` wave would activate, strongest first,
+    /// simulated from a clean slate. Activation, window, tick, traces and TD
+    /// eligibility stay as they are.
+    fn peek_spread(
+        &self,
+        seeds: Vec<(u64, f32)>,
+        integration: Option<String>,
+    ) -> PyResult<Vec<(u64, f32)>> {
+        for (node, _) in &seeds {
+            self.strict_check_node(*node, "peek_spread(seed)")?;
+        }
+        let integration = match integration {
+            Some(name) => Integration::parse(&name).map_err(PyValueError::new_err)?,
+            None => self.config.dynamics().integration,
+        };
+        self.count_peek();
+        let mut out: Vec<(u64, f32)> =
+            self.peek_spread_levels(&seeds, integration).into_iter().collect();
+        out.sort_by(|a, b| {
+            b.1.partial_cmp(&a.1).unwrap_or(std::cmp::Ordering::Equal).then(a.0.cmp(&b.0))
+        });
+        Ok(out)
+    }
+
+    /// Fresh working state over this engine's graph; see `ActivationContext`.
+    fn new_context(&self, name: String) -> ActivationContext {
+        let state = WorkingState {
+            activation_tick: self.tick,
+            ..WorkingState::default()
+        };
+        ActivationContext {
+            name,
+            engine_id: self.engine_id,
+            state,
+        }
+    }
+
+    /// Read-only handle whose methods are the `peek_*` queries; hand it to
+    /// callers that must not change engine state.
+    fn query_view(slf: Py<Self>) -> QueryView {
+        QueryView { engine: slf }
+    }
+
+    /// Copy-on-write overlay for what-if runs: it reads this engine's base chunks
+    /// and starts from copies of its delta, activation, learning state and tick,
+    /// but its writes (edges, learned nodes, config, drives, values, roles) stay
+    /// in memory. Merge them back with `commit_fork` or drop them with `discard`.
+    /// While it is alive this engine refuses `consolidate` and registry rewrites.
+    fn fork(&self) -> PyResult<RagpEngine> {
+        if self.fork.is_some() {
+            return Err(PyValueError::new_err("fork: cannot fork a fork"));
+        }
+        let capacity = NonZeroUsize::new(self.config.lru_capacity.max(1)).unwrap();
+        Ok(RagpEngine {
+            engine_id: NEXT_ENGINE_ID.fetch_add(1, Ordering::Relaxed),
+            storage: self.storage.clone(),
+            node_index: self.node_index.clone(),
+            delta_index: self.delta_index.clone(),
+            base_format: self.base_format,
+            consolidated_tick: self.consolidated_tick,
+            evictions: 0,
+            peeks: AtomicU64::new(0),
+            pending_nodes: self.pending_nodes.clone(),
+            retired_nodes: self.retired_nodes.clone(),
+            next_learned_id: self.next_learned_id,
+            activation: self.activation.clone(),
+            activation_tick: self.activation_tick,
+            temporal_window: self.temporal_window.clone(),
+            stdp_tick: self.stdp_tick,
+            tick: self.tick,
+            base_cache: LruCache::new(capacity),
+            pinned_cache: self.pinned_cache.clone(),
+            pinned_set: self.pinned_set.clone(),
+            access_count: self.access_count.clone(),
+            access_since_recompute: self.access_since_recompute,
+            config: self.config.clone(),
+            stored_config: self.stored_config.clone(),
+            last_consolidation: self.last_consolidation.clone(),
+            last_settle: self.last_settle.clone(),
+            trace: self.trace.clone(),
+            rng_state: self.rng_state,
+            drives: self.drives.clone(),
+            values_dirty: false,
+            td: self.td.clone(),
+            concepts: self.concepts.clone(),
+            concept_candidates: self.concept_candidates.clone(),
+            cache_budget_bytes: self.cache_budget_bytes,
+            pinned_budget_bytes: self.pinned_budget_bytes,
+            lru_budget_bytes: self.lru_budget_bytes,
+            // Only the pinned part of the cache is carried over.
+            cache_bytes_est: self.pinned_bytes_est,
+            pinned_bytes_est: self.pinned_bytes_est,
+            lru_bytes_est: 0,
+            registry_version: self.registry_version,
+            loaded_registry_version: self.loaded_registry_version,
+            async_state: Self::default_async_state(),
+            async_runtime: None,
+            base_lease: Arc::clone(&self.base_lease),
+            fork: Some(ForkState {
+                journal: RefCell::new(Vec::new()),
+                tick: self.tick,
+                next_learned_id: self.next_learned_id,
+                values: self
+                    .node_index
+                    .iter()
+                    .filter(|(_, m)| m.value != 0.0)
+                    .map(|(n, m)| (*n, m.value))
+                    .collect(),
+                drives: self.drives.clone(),
+                config: self.config.clone(),
+                closed: false,
+            }),
+        })
+    }
+
+    /// Replays a fork's journal onto this engine and its delta.bin, shifting
+    /// timestamps by the ticks this engine advanced since the fork, and adopts
+    /// concepts the fork formed. Node values, drives and config keys the fork
+    /// changed are merged, or the commit fails when the parent changed them too.
+    /// Role edits of innate nodes stay with the fork. The fork is closed afterwards.
+    fn commit_fork(&mut self, mut fork: PyRefMut<'_, RagpEngine>) -> PyResult<PyObject> {
+        if self.fork.is_some() {
+            return Err(PyValueError::new_err("commit_fork: call it on the parent engine"));
+        }
+        let (fork_tick, fork_next_learned) = match &fork.fork {
+            None => return Err(PyValueError::new_err("commit_fork: argument is not a fork")),
+            Some(state) if state.closed => {
+                return Err(PyValueError::new_err(
+                    "commit_fork: fork was already committed or discarded",
+                ))
+            }
+            Some(state) => (state.tick, state.next_learned_id),
+        };
+        if !Arc::ptr_eq(&self.base_lease, &fork.base_lease) {
+            return Err(PyValueError::new_err("commit_fork: fork belongs to another engine"));
+        }
+        let changes = self.fork_changes(&fork)?;
+
+        let journal = fork.fork.as_ref().map_or_else(Vec::new, |f| f.journal.take());
+        let allocated = journal.iter().any(|r| {
+            r.flags & DELTA_FLAG_NODE != 0
+                && r.flags & DELTA_FLAG_TOMBSTONE == 0
+                && r.a >= fork_next_learned
+        });
+        if allocated && self.next_learned_id > fork_next_learned {
+            if let Some(state) = &fork.fork {
+                state.journal.replace(journal);
+            }
+            return Err(PyValueError::new_err(
+                "commit_fork: both engines allocated learned nodes since the fork; \
+                 their IDs may collide",
+            ));
+        }
+
+        if !changes.config.is_empty() {
+            self.update_config(&changes.config)?;
+            self.push_dynamics_to_async();
+            self.refresh_cache_budget();
+            self.recompute_pinned_set(true);
+        }
+
+        let shift = self.tick.saturating_sub(fork_tick);
+        let mut edges = 0_usize;
+        let mut nodes = 0_usize;
+        for mut rec in journal.iter().copied() {
+            rec.ts = rec.ts.saturating_add(shift);
+            self.append_delta_record(rec.a, rec.b, rec.weight, rec.ts, rec.kind, rec.flags);
+            self.replay_delta_record(&rec);
+            if rec.flags & DELTA_FLAG_NODE != 0 {
+                nodes += 1;
+            } else {
+                self.invalidate_sender_cache(rec.a);
+                edges += 1;
+            }
+        }
+        self.tick = self.tick.max(fork.tick.saturating_add(shift));
+
+        for &(node, value) in &changes.values {
+            if let Some(meta) = self.node_index.get_mut(&node) {
+                meta.value = value;
+            }
+        }
+        if !changes.values.is_empty() {
+            self.save_node_values();
+        }
+        for (name, drive) in &changes.drives {
+            match drive {
+                Some(drive) => self.drives.insert(name.clone(), drive.clone()),
+                None => self.drives.remove(name),
+            };
+        }
+        if !changes.drives.is_empty() {
+            self.save_drives();
+        }
+
+        let mut adopted = 0_usize;
+        for (node, concept) in &fork.concepts {
+            if self.node_index.contains_key(node) && !self.concepts.contains_key(node) {
+                self.concepts.insert(*node, concept.clone());
+                adopted += 1;
+            }
+        }
+        self.retain_live_concepts();
+        if adopted > 0 || nodes > 0 {
+            self.save_concepts();
+        }
+        if nodes > 0 {
+            self.refresh_async_graph();
+        }
+        fork.close_fork();
+
+        Python::with_gil(|py| {
+            let out = PyDict::new_bound(py);
+            out.set_item("ok", true)?;
+            out.set_item("records", journal.len())?;
+            out.set_item("edges", edges)?;
+            out.set_item("nodes", nodes)?;
+            out.set_item("concepts", adopted)?;
+            out.set_item("values", changes.values.len())?;
+            out.set_item("drives", changes.drives.len())?;
+            out.set_item("config", changes.config.len())?;
+            out.set_item("tick", self.tick)?;
+            Ok(out.to_object(py))
+        })
+    }
+
+    /// Drops a fork's overlay and releases its hold on the parent's base;
+    /// returns the number of journal records thrown away.
+    fn discard(&mut self) -> PyResult<usize> {
+        let Some(state) = &self.fork else {
+            return Err(PyValueError::new_err("discard: engine is not a fork"));
+        };
+        let dropped = state.journal.borrow().len();
+        self.close_fork();
+        Ok(dropped)
+    }
+
+    /// Merged outgoing edges of `sender` with their history, strongest first:
+    /// created_at, last_updated, update_count, age (ticks since the last write) and
+    /// whether the latest write is still in delta. Unknown history reads as `None`;
+    /// an edge from a pre-v3 base that was written since reports created_at 0.
+    fn get_connections_detailed(&mut self, sender: u64) -> PyResult<Vec<PyObject>> {
+        self.strict_check_node(sender, "get_connections_detailed(sender)")?;
+        if !self.node_index.contains_key(&sender) {
+            return Ok(Vec::new());
+        }
+        self.record_access(sender);
+        let base = self.get_cached_or_load_base(sender);
+        let mut synapses = self.fold_delta(sender, base);
+        synapses.sort_by(|a, b| {
+            b.weight.abs().partial_cmp(&a.weight.abs()).unwrap_or(std::cmp::Ordering::Equal)
+        });
+        let in_delta = self.delta_index.get(&sender);
+
+        Python::with_gil(|py| {
+            let mut rows: Vec<PyObject> = Vec::with_capacity(synapses.len());
+            for s in &synapses {
+                let known = s.has_history();
+                let row = PyDict::new_bound(py);
+                row.set_item("receiver", s.receiver_id)?;
+                row.set_item("weight", s.weight)?;
+                row.set_item("kind", s.kind.name())?;
+                row.set_item("created_at", known.then_some(s.created_at))?;
+                row.set_item("last_updated", known.then_some(s.last_updated))?;
+                row.set_item("update_count", s.update_count)?;
+                row.set_item("age", known.then(|| self.tick.saturating_sub(s.last_updated)))?;
+                row.set_item("in_delta", in_delta.is_some_and(|m| m.contains_key(&s.receiver_id)))?;
+                rows.push(row.to_object(py));
+            }
+            Ok(rows)
+        })
     }
 
-    fn spread_activation(&mut self, seed_node: u64, seed_strength: f32) -> PyResult<()> {
+    fn spread_activation(
+        &mut self,
+        seed_node: u64,
+        seed_strength: f32,
+        mut ctx: Option<PyRefMut<'_, ActivationContext>>,
+    ) -> PyResult<()> {
         self.strict_check_node(seed_node, "spread_activation(seed_node)")?;
-        self.activation.clear();
-        self.activation.insert(seed_node, seed_strength);
-        self.temporal_window.push_back((seed_node, seed_strength, self.tick));
-        if self.temporal_window.len() > TEMPORAL_WINDOW_SIZE {
-            self.temporal_window.pop_front();
+        let integration = self.config.dynamics().integration;
+        self.in_context(ctx.as_deref_mut(), |e| {
+            e.spread_seeds(&[(seed_node, seed_strength)], integration)
+        })
+    }
+
+    /// Propagates all seeds in one wave so convergent inputs can be integrated.
+    fn spread_activation_many(
+        &mut self,
+        seeds: Vec<(u64, f32)>,
+        integration: Option<String>,
+        mut ctx: Option<PyRefMut<'_, ActivationContext>>,
+    ) -> PyResult<()> {
+        for (node, _) in &seeds {
+            self.strict_check_node(*node, "spread_activation_many(seed)")?;
+        }
+        let integration = match integration {
+            Some(name) => Integration::parse(&name).map_err(PyValueError::new_err)?,
+            None => self.config.dynamics().integration,
+        };
+        self.in_context(ctx.as_deref_mut(), |e| e.spread_seeds(&seeds, integration))
+    }
+
+    /// Runs recurrent settling from `seeds` regardless of `spread_dynamics`.
+    fn settle_activation(
+        &mut self,
+        seeds: Vec<(u64, f32)>,
+        integration: Option<String>,
+        max_iters: Option<u32>,
+        epsilon: Option<f32>,
+        mut ctx: Option<PyRefMut<'_, ActivationContext>>,
+    ) -> PyResult<PyObject> {
+        for (node, _) in &seeds {
+            self.strict_check_node(*node, "settle_activation(seed)")?;
+        }
+        let integration = match integration {
+            Some(name) => Integration::parse(&name).map_err(PyValueError::new_err)?,
+            None => self.config.dynamics().integration,
+        };
+        let max_iters = max_iters.unwrap_or(self.config.settle_max_iters);
+        let epsilon = epsilon.unwrap_or(self.config.settle_epsilon);
+        if max_iters == 0 {
+            return Err(PyValueError::new_err("max_iters must be >= 1"));
+        }
+        if !epsilon.is_finite() || epsilon <= 0.0 {
+            return Err(PyValueError::new_err(format!("epsilon must be > 0, got {}", epsilon)));
         }
+        let report = self.in_context(ctx.as_deref_mut(), |e| {
+            e.settle_seeds(&seeds, integration, max_iters, epsilon)
+        })?;
+        Self::settle_report_dict(&report)
+    }
 
-        let mut queue: VecDeque<(u64, f32, u8)> = VecDeque::new();
-        queue.push_back((seed_node, seed_strength, 0));
+    /// Pattern completion: clamps `partial_nodes` at 1.0, settles over the graph and
+    /// returns the `top_k` most strongly implied other nodes. Uses the configured
+    /// integration and settling limits, and changes no engine state (activation,
+    /// window, tick, access counts or caches).
+    fn recall(&self, partial_nodes: Vec<u64>, top_k: Option<usize>) -> PyResult<PyObject> {
+        for node in &partial_nodes {
+            self.strict_check_node(*node, "recall(partial_nodes)")?;
+        }
+        self.count_peek();
+        let top_k = top_k.unwrap_or(DEFAULT_RECALL_TOP_K);
+        let integration = self.config.dynamics().integration;
+        let cue: Vec<(u64, f32)> = partial_nodes.iter().map(|n| (*n, 1.0)).collect();
+        // Cost, opportunity and transition edges are not associations between
+        // co-occurring nodes, so they take no part in pattern completion.
+        let kind = Some(SynapseKind::Association);
+        let (levels, via, report) = self.peek_settle(&cue, integration, kind);
+        let mut recalled: Vec<(u64, f32)> = levels
+            .into_iter()
+            .filter(|(n, _)| !partial_nodes.contains(n))
+            .collect();
+        recalled.sort_by(|a, b| {
+            b.1.partial_cmp(&a.1).unwrap_or(std::cmp::Ordering::Equal).then(a.0.cmp(&b.0))
+        });
+        recalled.truncate(top_k);
 
-        while let Some((node, strength, depth)) = queue.pop_front() {
-            if depth >= MAX_SPREAD_DEPTH {
-                continue;
+        Python::with_gil(|py| {
+            let mut rows: Vec<PyObject> = Vec::with_capacity(recalled.len());
+            for (node, score) in &recalled {
+                let row = PyDict::new_bound(py);
+                row.set_item("node", *node)?;
+                row.set_item("score", *score)?;
+                row.set_item("via", via.get(node).copied())?;
+                row.set_item("role", self.role_of(*node).name())?;
+                rows.push(row.to_object(py));
             }
+            let out = PyDict::new_bound(py);
+            out.set_item("ok", true)?;
+            out.set_item("cue", partial_nodes.clone())?;
+            out.set_item("recalled", rows)?;
+            out.set_item("iterations", report.iterations)?;
+            out.set_item("converged", report.converged)?;
+            Ok(out.to_object(py))
+        })
+    }
 
-            let connections = self.get_connections_internal(node);
-            for (receiver, weight) in connections {
-                let incoming = strength * weight;
-                let threshold = self
-                    .node_index
-                    .get(&receiver)
-                    .map_or(DEFAULT_THRESHOLD, |m| m.threshold);
-                if incoming < threshold {
-                    continue;
-                }
+    /// Report of the last settling run in `ctx` (the engine's own without one);
+    /// `None` if settling never ran there.
+    fn get_settle_report(
+        &self,
+        ctx: Option<PyRef<'_, ActivationContext>>,
+    ) -> PyResult<Option<PyObject>> {
+        let last = match ctx.as_deref() {
+            Some(ctx) => {
+                self.check_context(ctx)?;
+                ctx.state.last_settle.as_ref()
+            }
+            None => self.last_settle.as_ref(),
+        };
+        last.map(Self::settle_report_dict).transpose()
+    }
 
-                let current = self.activation.get(&receiver).copied().unwrap_or(0.0);
-                if incoming > current {
-                    self.activation.insert(receiver, incoming);
-                    self.temporal_window.push_back((receiver, incoming, self.tick));
-                    if self.temporal_window.len() > TEMPORAL_WINDOW_SIZE {
-                        self.temporal_window.pop_front();
-                    }
-                    queue.push_back((receiver, incoming, depth.saturating_add(1)));
-                }
+    fn set_spread_dynamics(
+        &mut self,
+        mode: String,
+        max_iters: Option<u32>,
+        epsilon: Option<f32>,
+    ) -> PyResult<PyObject> {
+        let mut updates = vec![("spread_dynamics", ConfigValue::Str(mode))];
+        if let Some(v) = max_iters {
+            updates.push(("settle_max_iters", ConfigValue::Int(v as i64)));
+        }
+        if let Some(v) = epsilon {
+            updates.push(("settle_epsilon", ConfigValue::Float(v as f64)));
+        }
+        self.update_config(&updates)?;
+
+        Python::with_gil(|py| {
+            let out = PyDict::new_bound(py);
+            out.set_item("ok", true)?;
+            out.set_item("spread_dynamics", self.config.spread_dynamics.clone())?;
+            out.set_item("settle_max_iters", self.config.settle_max_iters)?;
+            out.set_item("settle_epsilon", self.config.settle_epsilon)?;
+            Ok(out.to_object(py))
+        })
+    }
+
+    fn set_activation_dynamics(
+        &mut self,
+        mode: Option<String>,
+        decay: Option<f32>,
+        floor: Option<f32>,
+        integration: Option<String>,
+    ) -> PyResult<PyObject> {
+        let mut updates = Vec::new();
+        if let Some(m) = mode {
+            updates.push(("activation_mode", ConfigValue::Str(m)));
+        }
+        if let Some(name) = integration {
+            updates.push(("integration", ConfigValue::Str(name)));
+        }
+        if let Some(v) = decay {
+            updates.push(("activation_decay", ConfigValue::Float(v as f64)));
+        }
+        if let Some(v) = floor {
+            updates.push(("activation_floor", ConfigValue::Float(v as f64)));
+        }
+        self.update_config(&updates)?;
+        self.activation_tick = self.tick;
+        self.push_dynamics_to_async();
+
+        Python::with_gil(|py| {
+            let out = PyDict::new_bound(py);
+            out.set_item("ok", true)?;
+            out.set_item("activation_mode", self.config.activation_mode.clone())?;
+            out.set_item("activation_decay", self.config.activation_decay)?;
+            out.set_item("activation_floor", self.config.activation_floor)?;
+            out.set_item("integration", self.config.integration.clone())?;
+            Ok(out.to_object(py))
+        })
+    }
+
+    /// Basal-ganglia style action selection: candidates from every stimulus in
+    /// `stimuli` compete through mutual inhibition, then the go/no-go gate and
+    /// softmax / epsilon-greedy exploration pick the winner.
+    fn select_action(
+        &mut self,
+        stimuli: Vec<u64>,
+        context: Vec<u64>,
+        temperature: Option<f32>,
+        epsilon: Option<f32>,
+        mut ctx: Option<PyRefMut<'_, ActivationContext>>,
+    ) -> PyResult<PyObject> {
+        self.in_context(ctx.as_deref_mut(), |e| {
+            e.select_action_internal(stimuli, context, temperature, epsilon)
+        })?
+    }
+
+    /// Multi-step lookahead: ranks action sequences from `stimulus` by expected
+    /// value, treating action->sensor `transition` edges as predicted outcomes.
+    /// Runs on a read-only snapshot of merged base+delta weights; nothing is recorded.
+    fn plan(
+        &self,
+        stimulus: u64,
+        context: Vec<u64>,
+        horizon: Option<u32>,
+        beam_width: Option<u32>,
+    ) -> PyResult<PyObject> {
+        let horizon = horizon.unwrap_or(self.config.plan_horizon);
+        let beam_width = beam_width.unwrap_or(self.config.plan_beam_width);
+        if horizon == 0 || horizon > 16 {
+            return Err(PyValueError::new_err(format!(
+                "horizon must be in [1, 16], got {}",
+                horizon
+            )));
+        }
+        if beam_width == 0 || beam_width > 64 {
+            return Err(PyValueError::new_err(format!(
+                "beam_width must be in [1, 64], got {}",
+                beam_width
+            )));
+        }
+        let states = [NodeRole::Sensor, NodeRole::Homeostatic];
+        self.strict_check_node(stimulus, "plan(stimulus)")?;
+        self.strict_check_role(stimulus, &states, "plan(stimulus)")?;
+        for ctx in &context {
+            self.strict_check_node(*ctx, "plan(context)")?;
+            self.strict_check_role(*ctx, &[NodeRole::Context, NodeRole::Sensor], "plan(context)")?;
+        }
+        self.count_peek();
+
+        let (plans, expanded, snapshot) =
+            self.plan_search(stimulus, &context, horizon, beam_width as usize);
+        Python::with_gil(|py| {
+            let out = PyDict::new_bound(py);
+            out.set_item("ok", true)?;
+            out.set_item("stimulus", stimulus)?;
+            out.set_item("horizon", horizon)?;
+            out.set_item("beam_width", beam_width)?;
+            out.set_item("discount", self.config.td_gamma)?;
+            out.set_item("expanded", expanded)?;
+            out.set_item("snapshot_nodes", snapshot)?;
+            out.set_item("best", plans.first().map(|p| p.actions.clone()))?;
+
+            let mut rows: Vec<PyObject> = Vec::with_capacity(plans.len());
+            for p in &plans {
+                let row = PyDict::new_bound(py);
+                row.set_item("actions", p.actions.clone())?;
+                row.set_item("states", p.states.clone())?;
+                row.set_item("steps", p.actions.len())?;
+                row.set_item("probability", p.probability)?;
+                row.set_item("expected_value", p.expected_value)?;
+                rows.push(row.to_object(py));
+            }
+            out.set_item("plans", rows)?;
+            Ok(out.to_object(py))
+        })
+    }
+
+    /// Sets drive `name` to `level` in [0, 1]. `node` binds the drive to its sensor
+    /// node; it is required the first time and may rebind later.
+    fn set_drive(&mut self, name: String, level: f32, node: Option<u64>) -> PyResult<PyObject> {
+        let name = name.trim().to_ascii_lowercase();
+        if name.is_empty() {
+            return Err(PyValueError::new_err("drive name must not be empty"));
+        }
+        if !level.is_finite() || !(0.0..=1.0).contains(&level) {
+            return Err(PyValueError::new_err(format!(
+                "drive level must be in [0, 1], got {}",
+                level
+            )));
+        }
+        let node = match (node, self.drives.get(&name)) {
+            (Some(n), _) => n,
+            (None, Some(existing)) => existing.node,
+            (None, None) => {
+                return Err(PyValueError::new_err(format!(
+                    "drive '{}' is not bound yet; pass node",
+                    name
+                )))
+            }
+        };
+        self.strict_check_node(node, "set_drive(node)")?;
+        self.strict_check_role(node, &[NodeRole::Sensor, NodeRole::Homeostatic], "set_drive(node)")?;
+        if let Some((other, _)) = self.drives.iter().find(|(n, d)| d.node == node && **n != name) {
+            return Err(PyValueError::new_err(format!(
+                "node {} is already bound to drive '{}'",
+                node, other
+            )));
+        }
+
+        let changed = self
+            .drives
+            .get(&name)
+            .is_none_or(|d| d.node != node || d.level != level);
+        if changed {
+            self.drives.insert(name.clone(), DriveState { node, level });
+            self.save_drives();
+        }
+
+        Python::with_gil(|py| {
+            let out = PyDict::new_bound(py);
+            out.set_item("ok", true)?;
+            out.set_item("name", name)?;
+            out.set_item("node", node)?;
+            out.set_item("level", level)?;
+            Ok(out.to_object(py))
+        })
+    }
+
+    /// Drives as `{name: {"node": id, "level": x}}`.
+    fn get_drives(&self) -> PyResult<PyObject> {
+        Python::with_gil(|py| {
+            let out = PyDict::new_bound(py);
+            for (name, drive) in &self.drives {
+                let entry = PyDict::new_bound(py);
+                entry.set_item("node", drive.node)?;
+                entry.set_item("level", drive.level)?;
+                out.set_item(name, entry)?;
+            }
+            Ok(out.to_object(py))
+        })
+    }
+
+    fn clear_drive(&mut self, name: String) -> bool {
+        let removed = self.drives.remove(&name.trim().to_ascii_lowercase()).is_some();
+        if removed {
+            self.save_drives();
+        }
+        removed
+    }
+
+    /// TD(lambda) step: `reward` was earned in the state given by `nodes` (default:
+    /// nodes seeded or selected since the last call). The previous step is updated
+    /// now that its successor is known; `terminal` also closes the current step.
+    fn apply_reward(
+        &mut self,
+        reward: f32,
+        nodes: Option<Vec<u64>>,
+        terminal: Option<bool>,
+        mut ctx: Option<PyRefMut<'_, ActivationContext>>,
+    ) -> PyResult<PyObject> {
+        self.in_context(ctx.as_deref_mut(), |e| e.apply_reward_internal(reward, nodes, terminal))?
+    }
+
+    /// TD error of the most recent update (`None` before the first one).
+    fn reward_prediction_error(&self) -> Option<f32> {
+        self.td.last_rpe
+    }
+
+    fn get_node_value(&self, node: u64) -> PyResult<f32> {
+        self.strict_check_node(node, "get_node_value")?;
+        Ok(self.node_value(node))
+    }
+
+    /// Non-zero learned node values.
+    fn get_node_values(&self) -> HashMap<u64, f32> {
+        self.node_index
+            .iter()
+            .filter(|(_, m)| m.value != 0.0)
+            .map(|(id, m)| (*id, m.value))
+            .collect()
+    }
+
+    /// Reseeds the engine RNG used by `select_action` exploration.
+    fn seed_rng(&mut self, seed: u64) {
+        self.rng_state = (seed ^ 0x9E37_79B9_7F4A_7C15).max(1);
+    }
+
+    /// Enables or disables activation provenance recording (sync and async paths).
+    fn set_trace_mode(&mut self, enabled: bool) -> PyResult<PyObject> {
+        self.update_config(&[("trace_activation", ConfigValue::Bool(enabled))])?;
+        if !enabled {
+            self.trace.clear();
+        }
+        self.push_dynamics_to_async();
+
+        Python::with_gil(|py| {
+            let out = PyDict::new_bound(py);
+            out.set_item("ok", true)?;
+            out.set_item("trace_activation", enabled)?;
+            Ok(out.to_object(py))
+        })
+    }
+
+    /// Path that activated `node`, from its seed down to the node itself, in `ctx`
+    /// (the engine's own state, or the async runtime's while it runs, without one).
+    fn explain_activation(
+        &self,
+        node: u64,
+        ctx: Option<PyRef<'_, ActivationContext>>,
+    ) -> PyResult<PyObject> {
+        let (path, complete, levels) = if let Some(ctx) = ctx.as_deref() {
+            self.check_context(ctx)?;
+            let (path, complete) = trace_path(&ctx.state.trace, node);
+            let levels = path.iter().map(|(id, _)| ctx.state.activation.get(id).copied()).collect();
+            (path, complete, levels)
+        } else if let Some(runtime) = self.async_runtime.as_ref() {
+            runtime.rt.block_on(async {
+                let s = runtime.shared.lock().await;
+                let (path, complete) = trace_path(&s.trace, node);
+                let levels: Vec<Option<f32>> =
+                    path.iter().map(|(id, _)| s.activation.get(id).copied()).collect();
+                (path, complete, levels)
+            })
+        } else {
+            let (path, complete) = trace_path(&self.trace, node);
+            let levels = path.iter().map(|(id, _)| self.activation.get(id).copied()).collect();
+            (path, complete, levels)
+        };
+
+        Python::with_gil(|py| {
+            let out = PyDict::new_bound(py);
+            out.set_item("node", node)?;
+            out.set_item("trace_enabled", self.config.trace_activation)?;
+            out.set_item("activation", levels.last().copied().flatten())?;
+            if path.is_empty() {
+                out.set_item("ok", false)?;
+                let reason = if self.config.trace_activation {
+                    "no recorded activation for node"
+                } else {
+                    "trace mode is off (set_trace_mode(True))"
+                };
+                out.set_item("reason", reason)?;
+                return Ok(out.to_object(py));
+            }
+            out.set_item("ok", true)?;
+            out.set_item("complete", complete)?;
+            out.set_item("seed", if complete { Some(path[0].0) } else { None })?;
+            out.set_item("depth", path.last().map(|(_, t)| t.depth))?;
+
+            let mut steps: Vec<PyObject> = Vec::with_capacity(path.len());
+            for ((id, t), current) in path.iter().zip(levels.iter()) {
+                let step = PyDict::new_bound(py);
+                step.set_item("node", *id)?;
+                step.set_item("predecessor", t.predecessor)?;
+                step.set_item("weight", t.weight)?;
+                step.set_item("incoming", t.incoming)?;
+                step.set_item("level", t.level)?;
+                step.set_item("activation", *current)?;
+                step.set_item("depth", t.depth)?;
+                step.set_item("tick", t.tick)?;
+                step.set_item("shard", t.shard)?;
+                step.set_item("via_hop", t.via_hop)?;
+                steps.push(step.to_object(py));
             }
+            out.set_item("path", steps)?;
+            Ok(out.to_object(py))
+        })
+    }
+
+    /// Decays the activation of `ctx` (the engine's own without one) by `ticks`
+    /// (default 1) without advancing the engine tick.
+    fn decay_activation(
+        &mut self,
+        ticks: Option<f32>,
+        mut ctx: Option<PyRefMut<'_, ActivationContext>>,
+    ) -> PyResult<usize> {
+        let ticks = ticks.unwrap_or(1.0);
+        if !ticks.is_finite() || ticks < 0.0 {
+            return Err(PyValueError::new_err(format!("ticks must be >= 0, got {}", ticks)));
+        }
+        let dynamics = self.config.dynamics();
+        if let Some(ctx) = ctx.as_deref_mut() {
+            self.check_context(ctx)?;
+            let state = &mut ctx.state;
+            decay_activation_map(&mut state.activation, &mut state.trace, ticks, &dynamics);
+            return Ok(state.activation.len());
+        }
+        if let Some(runtime) = self.async_runtime.as_ref() {
+            return Ok(runtime.rt.block_on(async {
+                let mut s = runtime.shared.lock().await;
+                let s = &mut *s;
+                decay_activation_map(&mut s.activation, &mut s.trace, ticks, &dynamics);
+                s.activation.len()
+            }));
         }
+        decay_activation_map(&mut self.activation, &mut self.trace, ticks, &dynamics);
+        Ok(self.activation.len())
+    }
 
-        self.tick = self.tick.saturating_add(1);
-        Ok(())
+    /// Advances the engine clock by `dt` ticks (default 1) and applies the decay owed
+    /// to the engine's own activation and, when given, to `ctx`'s.
+    fn step(
+        &mut self,
+        dt: Option<u32>,
+        mut ctx: Option<PyRefMut<'_, ActivationContext>>,
+    ) -> PyResult<u32> {
+        let dt = dt.unwrap_or(1);
+        if let Some(ctx) = ctx.as_deref() {
+            self.check_context(ctx)?;
+        }
+        if let Some(runtime) = self.async_runtime.as_ref() {
+            let dynamics = self.config.dynamics();
+            let now = runtime.global_tick.fetch_add(dt as u64, Ordering::SeqCst) + dt as u64;
+            runtime.rt.block_on(async {
+                let mut s = runtime.shared.lock().await;
+                let s = &mut *s;
+                let elapsed = now.saturating_sub(s.activation_tick);
+                decay_activation_map(&mut s.activation, &mut s.trace, elapsed as f32, &dynamics);
+                s.activation_tick = now;
+            });
+        }
+        self.tick = self.tick.saturating_add(dt);
+        self.decay_activation_to_tick();
+        if ctx.is_some() {
+            self.in_context(ctx.as_deref_mut(), |e| e.decay_activation_to_tick())?;
+        }
+        Ok(self.tick)
     }
 
     fn get_active_nodes(&self) -> Vec<(u64, f32)> {
@@ -1602,132 +6665,262 @@ impl RagpEngine {
         out
     }
 
-    fn compute_cd(&mut self, stimulus: u64, context: Vec<u64>) -> PyResult<Vec<(u64, f64)>> {
-        self.strict_check_node(stimulus, "compute_cd(stimulus)")?;
-        for ctx in &context {
-            self.strict_check_node(*ctx, "compute_cd(context)")?;
-        }
+    /// Cd per action for `stimulus` in `context`. Scoring reads no working state
+    /// today, so `ctx` only keeps call sites uniform across contexts.
+    fn compute_cd(
+        &mut self,
+        stimulus: u64,
+        context: Vec<u64>,
+        mut ctx: Option<PyRefMut<'_, ActivationContext>>,
+    ) -> PyResult<Vec<(u64, f64)>> {
+        let rows = self.in_context(ctx.as_deref_mut(), |e| {
+            e.cd_breakdown(stimulus, &context, "compute_cd")
+        })??;
+        Ok(rows
+            .iter()
+            .filter(|r| r.excluded.is_none())
+            .map(|r| (r.action, r.cd))
+            .collect())
+    }
 
-        let actions = self.get_connections_internal(stimulus);
-        if actions.is_empty() {
-            return Ok(Vec::new());
+    /// `compute_cd` with every component exposed, for debugging action choice.
+    /// Receivers outside `allowed` (when given) are listed under `excluded`.
+    fn compute_cd_explain(
+        &mut self,
+        stimulus: u64,
+        context: Vec<u64>,
+        allowed: Option<Vec<u64>>,
+        mut ctx: Option<PyRefMut<'_, ActivationContext>>,
+    ) -> PyResult<PyObject> {
+        let mut rows = self.in_context(ctx.as_deref_mut(), |e| {
+            e.cd_breakdown(stimulus, &context, "compute_cd_explain")
+        })??;
+        if let Some(allowed) = allowed {
+            let allowed: HashSet<u64> = allowed.into_iter().collect();
+            for row in rows.iter_mut() {
+                if row.excluded.is_none() && !allowed.contains(&row.action) {
+                    row.excluded = Some("not in allowed action set".to_string());
+                }
+            }
         }
+        let (kept, excluded): (Vec<CdBreakdown>, Vec<CdBreakdown>) =
+            rows.into_iter().partition(|r| r.excluded.is_none());
 
-        let mut out: Vec<(u64, f64)> = Vec::new();
-        for (action_id, value) in &actions {
-            let cost_conns = self.get_connections_internal(*action_id);
-            let cost = if cost_conns.is_empty() {
-                1.0_f64
-            } else {
-                let total: f32 = cost_conns.iter().map(|(_, w)| *w).sum();
-                (total / cost_conns.len() as f32) as f64
+        Python::with_gil(|py| {
+            let row_dict = |r: &CdBreakdown| -> PyResult<Bound<'_, PyDict>> {
+                let row = PyDict::new_bound(py);
+                row.set_item("action", r.action)?;
+                row.set_item("cd", r.cd)?;
+                row.set_item("value", r.value)?;
+                row.set_item("raw_value", r.raw_value)?;
+                row.set_item("drive_gain", r.drive_gain)?;
+                row.set_item("drive", r.drive.clone())?;
+                row.set_item("learned_value", r.learned_value)?;
+                row.set_item("cost", r.cost)?;
+                row.set_item("cost_default", r.cost_default)?;
+                row.set_item("cost_edges", r.cost_edges)?;
+                row.set_item("opportunity", r.opportunity)?;
+                row.set_item("opportunity_default", r.opportunity_default)?;
+                row.set_item("context_edges", r.context_edges.clone())?;
+                row.set_item("cost_zero", r.cost == 0.0)?;
+                row.set_item("inhibitory", r.value < 0.0 || r.opportunity < 0.0)?;
+                Ok(row)
             };
 
-            let mut opp_weights: Vec<f64> = Vec::new();
-            for ctx in &context {
-                for (target, w) in self.get_connections_internal(*ctx) {
-                    if target == *action_id {
-                        opp_weights.push(w as f64);
-                    }
-                }
+            let out = PyDict::new_bound(py);
+            out.set_item("ok", true)?;
+            out.set_item("stimulus", stimulus)?;
+            out.set_item("context", context.clone())?;
+            out.set_item("default_cost", DEFAULT_CD_COST)?;
+            out.set_item("min_cost", self.config.cd_min_cost)?;
+            out.set_item("default_opportunity", DEFAULT_CD_OPPORTUNITY)?;
+            out.set_item("value_source", self.config.cd_value_source.clone())?;
+            if kept.is_empty() {
+                let reason = if excluded.is_empty() {
+                    "stimulus has no outgoing synapses"
+                } else {
+                    "every receiver was excluded"
+                };
+                out.set_item("reason", reason)?;
             }
 
-            let opportunity = if opp_weights.is_empty() {
-                0.5
-            } else {
-                opp_weights.iter().sum::<f64>() / opp_weights.len() as f64
-            };
-
-            let cd = if cost == 0.0 {
-                f64::MAX
+            let mut actions: Vec<PyObject> = Vec::with_capacity(kept.len());
+            for (rank, r) in kept.iter().enumerate() {
+                let row = row_dict(r)?;
+                row.set_item("rank", rank + 1)?;
+                actions.push(row.to_object(py));
+            }
+            let mut dropped: Vec<PyObject> = Vec::with_capacity(excluded.len());
+            for r in &excluded {
+                let row = row_dict(r)?;
+                row.set_item("role", self.role_of(r.action).name())?;
+                row.set_item("reason", r.excluded.clone())?;
+                dropped.push(row.to_object(py));
+            }
+            out.set_item("actions", actions)?;
+            out.set_item("excluded", dropped)?;
+            Ok(out.to_object(py))
+        })
+    }
+    /// Learns from the temporal window of `ctx` (the engine's own without one);
+    /// every context writes to the same delta log.
+    fn form_synapses_from_window(
+        &mut self,
+        mut ctx: Option<PyRefMut<'_, ActivationContext>>,
+    ) -> PyResult<u32> {
+        self.in_context(ctx.as_deref_mut(), |e| {
+            let formed = if e.config.learning_mode == "stdp" {
+                let (potentiated, depressed, created) = e.form_synapses_stdp();
+                potentiated.saturating_add(depressed).saturating_add(created)
             } else {
-                (*value as f64 * opportunity) / cost
+                e.form_synapses_hebbian()
             };
-            out.push((*action_id, cd));
-        }
-
-        out.sort_by(|a, b| b.1.partial_cmp(&a.1).unwrap_or(std::cmp::Ordering::Equal));
-        Ok(out)
+            e.observe_coactivation();
+            formed
+        })
     }
-    fn form_synapses_from_window(&mut self) -> u32 {
-        let nodes: Vec<(u64, f32)> = self
-            .temporal_window
-            .iter()
-            .map(|(node_id, strength, _)| (*node_id, *strength))
-            .collect();
 
-        let mut formed = 0_u32;
-        for i in 0..nodes.len() {
-            let (sender, s_strength) = nodes[i];
-            if !self.node_index.contains_key(&sender) {
-                continue;
+    /// Learned concepts, by node: `{node, members, support, formed_at}`.
+    fn list_concepts(&self) -> PyResult<Vec<PyObject>> {
+        let mut nodes: Vec<&u64> = self.concepts.keys().collect();
+        nodes.sort_unstable();
+        Python::with_gil(|py| {
+            let mut rows: Vec<PyObject> = Vec::with_capacity(nodes.len());
+            for node in nodes {
+                let concept = &self.concepts[node];
+                let row = PyDict::new_bound(py);
+                row.set_item("node", *node)?;
+                row.set_item("members", concept.members.clone())?;
+                row.set_item("support", concept.support)?;
+                row.set_item("formed_at", concept.formed_at)?;
+                rows.push(row.to_object(py));
             }
+            Ok(rows)
+        })
+    }
 
-            let sender_thr = self
-                .node_index
-                .get(&sender)
-                .map_or(DEFAULT_THRESHOLD, |m| m.threshold);
-            if s_strength < sender_thr {
-                continue;
-            }
-            if self.synapse_count_for(sender) >= MAX_SYNAPSES_PER_NODE {
-                continue;
+    fn set_learning_mode(
+        &mut self,
+        mode: String,
+        a_plus: Option<f32>,
+        a_minus: Option<f32>,
+        tau_plus: Option<f32>,
+        tau_minus: Option<f32>,
+    ) -> PyResult<PyObject> {
+        let mut updates = vec![("learning_mode", ConfigValue::Str(mode))];
+        for (key, v) in [
+            ("stdp_a_plus", a_plus),
+            ("stdp_a_minus", a_minus),
+            ("stdp_tau_plus", tau_plus),
+            ("stdp_tau_minus", tau_minus),
+        ] {
+            if let Some(x) = v {
+                updates.push((key, ConfigValue::Float(x as f64)));
             }
+        }
+        self.update_config(&updates)?;
 
-            for j in 0..nodes.len() {
-                if i == j {
-                    continue;
-                }
+        Python::with_gil(|py| {
+            let out = PyDict::new_bound(py);
+            out.set_item("ok", true)?;
+            out.set_item("learning_mode", self.config.learning_mode.clone())?;
+            out.set_item("a_plus", self.config.stdp_a_plus)?;
+            out.set_item("a_minus", self.config.stdp_a_minus)?;
+            out.set_item("tau_plus", self.config.stdp_tau_plus)?;
+            out.set_item("tau_minus", self.config.stdp_tau_minus)?;
+            Ok(out.to_object(py))
+        })
+    }
 
-                let (receiver, r_strength) = nodes[j];
-                if !self.node_index.contains_key(&receiver) {
-                    continue;
-                }
+    fn get_config(&self) -> EngineConfig {
+        self.config.clone()
+    }
 
-                let prob = s_strength * r_strength;
-                if rand_f32() > prob {
-                    continue;
-                }
+    fn set_cache_policy(
+        &mut self,
+        policy: String,
+        ram_fraction: Option<f32>,
+        pin_fraction: Option<f32>,
+        min_mb: Option<u64>,
+        max_mb: Option<u64>,
+    ) -> PyResult<PyObject> {
+        let mut updates = vec![("cache_policy", ConfigValue::Str(policy))];
+        if let Some(v) = ram_fraction {
+            updates.push(("cache_ram_fraction", ConfigValue::Float(v as f64)));
+        }
+        if let Some(v) = pin_fraction {
+            updates.push(("cache_pin_fraction", ConfigValue::Float(v as f64)));
+        }
+        if let Some(v) = min_mb {
+            updates.push(("cache_ram_min_mb", ConfigValue::Int(v as i64)));
+        }
+        if let Some(v) = max_mb {
+            updates.push(("cache_ram_max_mb", ConfigValue::Int(v as i64)));
+        }
 
-                let in_delta = self
-                    .delta_index
-                    .get(&sender)
-                    .map_or(false, |m| m.contains_key(&receiver));
-                if in_delta {
-                    continue;
-                }
+        let pinned_before: HashSet<u64> = self.pinned_cache.keys().copied().collect();
+        let lru_before: HashSet<u64> = self.base_cache.iter().map(|(k, _)| *k).collect();
 
-                let base_syn = self.get_cached_or_load_base(sender);
-                let in_base = base_syn.iter().any(|s| s.receiver_id == receiver);
-                if in_base {
-                    continue;
-                }
+        self.update_config(&updates)?;
+        self.access_since_recompute = 0;
+        self.refresh_cache_budget();
+        self.recompute_pinned_set(true);
 
-                let ts = self.tick;
-                let entry = DeltaEntry {
-                    sender_id: sender,
-                    receiver_id: receiver,
-                    weight: INITIAL_WEIGHT,
-                    timestamp: ts,
-                };
-                self.append_delta_entry(&entry);
-                self.delta_index
-                    .entry(sender)
-                    .or_default()
-                    .insert(receiver, (INITIAL_WEIGHT, ts));
-                self.invalidate_sender_cache(sender);
-                formed = formed.saturating_add(1);
-            }
-        }
+        let demoted = pinned_before
+            .iter()
+            .filter(|k| !self.pinned_cache.contains_key(k) && self.base_cache.contains(k))
+            .count();
+        let promoted = lru_before
+            .iter()
+            .filter(|k| self.pinned_cache.contains_key(k))
+            .count();
 
-        formed
+        Python::with_gil(|py| {
+            let out = PyDict::new_bound(py);
+            out.set_item("ok", true)?;
+            out.set_item("cache_policy", self.config.cache_policy.clone())?;
+            out.set_item("cache_ram_fraction", self.config.cache_ram_fraction)?;
+            out.set_item("cache_pin_fraction", self.config.cache_pin_fraction)?;
+            out.set_item("cache_ram_min_mb", self.config.cache_ram_min_mb)?;
+            out.set_item("cache_ram_max_mb", self.config.cache_ram_max_mb)?;
+            out.set_item("cache_budget_mb", self.cache_budget_bytes as f64 / (1024.0 * 1024.0))?;
+            out.set_item("pinned_budget_mb", self.pinned_budget_bytes as f64 / (1024.0 * 1024.0))?;
+            out.set_item("lru_budget_mb", self.lru_budget_bytes as f64 / (1024.0 * 1024.0))?;
+            out.set_item("pinned_nodes", self.pinned_cache.len())?;
+            out.set_item("lru_nodes", self.base_cache.len())?;
+            out.set_item("promoted", promoted)?;
+            out.set_item("demoted", demoted)?;
+            Ok(out.to_object(py))
+        })
     }
 
-    fn update_weight(&mut self, sender: u64, receiver: u64, new_weight: f32) -> PyResult<()> {
+    /// Sets the sender->receiver weight. An edge has one kind: without `kind` an
+    /// existing edge keeps its kind and a new one is an association, and a `kind`
+    /// that differs from the existing edge's is rejected (see `retype_edge`).
+    fn update_weight(
+        &mut self,
+        sender: u64,
+        receiver: u64,
+        new_weight: f32,
+        kind: Option<String>,
+    ) -> PyResult<()> {
         self.strict_check_node(sender, "update_weight(sender)")?;
         self.strict_check_node(receiver, "update_weight(receiver)")?;
+        let current = self.current_edge_for(sender, receiver).map(|(_, k)| k);
+        let kind = match kind {
+            Some(name) => SynapseKind::parse(&name).map_err(PyValueError::new_err)?,
+            None => current.unwrap_or(SynapseKind::Association),
+        };
+        if let Some(existing) = current.filter(|k| *k != kind) {
+            return Err(PyValueError::new_err(format!(
+                "edge {}->{} is already a {} synapse; use retype_edge to change its kind",
+                sender,
+                receiver,
+                existing.name()
+            )));
+        }
 
-        let weight = new_weight.max(0.0).min(1.0);
+        let weight = new_weight.clamp(-1.0, 1.0);
         if let Some(runtime) = self.async_runtime.as_ref() {
             let owner = self.owner_shard(sender);
             let (tx, rx) = oneshot::channel();
@@ -1748,24 +6941,68 @@ impl RagpEngine {
 
         let ts = self.tick;
         self.tick = self.tick.saturating_add(1);
+        self.write_delta_weight(sender, receiver, weight, ts, kind);
+        Ok(())
+    }
 
-        self.delta_index
-            .entry(sender)
-            .or_default()
-            .insert(receiver, (weight, ts));
-
-        let entry = DeltaEntry {
-            sender_id: sender,
-            receiver_id: receiver,
-            weight,
-            timestamp: ts,
+    /// Changes the kind of an existing sender->receiver edge, keeping its weight.
+    /// Returns False when there is no such edge.
+    fn retype_edge(&mut self, sender: u64, receiver: u64, kind: String) -> PyResult<bool> {
+        self.strict_check_node(sender, "retype_edge(sender)")?;
+        self.strict_check_node(receiver, "retype_edge(receiver)")?;
+        let kind = SynapseKind::parse(&kind).map_err(PyValueError::new_err)?;
+        let Some((weight, current)) = self.current_edge_for(sender, receiver) else {
+            return Ok(false);
         };
-        self.append_delta_entry(&entry);
-        self.invalidate_sender_cache(sender);
-        Ok(())
+        if current != kind {
+            let ts = self.tick;
+            self.tick = self.tick.saturating_add(1);
+            self.write_delta_weight(sender, receiver, weight, ts, kind);
+        }
+        Ok(true)
+    }
+
+    fn set_prune_strategy(&mut self, strategy: String, ranges: Option<String>) -> PyResult<PyObject> {
+        let mut updates = vec![("prune_strategy", ConfigValue::Str(strategy))];
+        if let Some(r) = ranges {
+            updates.push(("prune_ranges", ConfigValue::Str(r)));
+        }
+        self.update_config(&updates)?;
+
+        Python::with_gil(|py| {
+            let out = PyDict::new_bound(py);
+            out.set_item("ok", true)?;
+            out.set_item("prune_strategy", self.config.prune_strategy.clone())?;
+            out.set_item("prune_ranges", self.config.prune_ranges.clone())?;
+            Ok(out.to_object(py))
+        })
+    }
+
+    fn get_consolidation_report(&self) -> PyResult<PyObject> {
+        let r = &self.last_consolidation;
+        Python::with_gil(|py| {
+            let out = PyDict::new_bound(py);
+            out.set_item("merged", r.merged)?;
+            out.set_item("pruned", r.pruned)?;
+            out.set_item("decayed", r.decayed)?;
+            out.set_item("kept", r.kept)?;
+            out.set_item("tick", r.tick)?;
+            out.set_item("strategy", r.strategy.clone())?;
+            out.set_item("ranges", r.ranges.clone())?;
+            let rows = PyDict::new_bound(py);
+            for (spec, (nodes, pruned)) in &r.per_strategy {
+                let row = PyDict::new_bound(py);
+                row.set_item("nodes", *nodes)?;
+                row.set_item("pruned", *pruned)?;
+                rows.set_item(spec.clone(), row)?;
+            }
+            out.set_item("per_strategy", rows)?;
+            Ok(out.to_object(py))
+        })
     }
 
-    fn consolidate(&mut self) -> (u32, u32) {
+    fn consolidate(&mut self) -> PyResult<(u32, u32)> {
+        self.check_base_writable("consolidate")?;
         let async_exists = self.async_runtime.is_some();
         if async_exists {
             if let Some(runtime) = self.async_runtime.as_ref() {
@@ -1776,51 +7013,29 @@ impl RagpEngine {
                 for tx in &runtime.shard_txs {
                     let (ack_tx, ack_rx) = oneshot::channel();
                     let _ = tx.send(ShardCommand::Flush { reply: ack_tx });
-                    let _ = runtime.rt.block_on(async { ack_rx.await });
+                    let _ = runtime.rt.block_on(ack_rx);
                 }
                 self.sync_async_state_from_shared();
             }
         }
 
-        let mut merged = 0_u32;
-        let mut pruned = 0_u32;
-
-        let senders: Vec<u64> = self.delta_index.keys().copied().collect();
-        for sender in &senders {
-            let mut synapses = self.load_from_base(*sender);
-            if let Some(delta) = self.delta_index.get(sender) {
-                for (receiver, (weight, _)) in delta {
-                    if let Some(existing) = synapses.iter_mut().find(|s| s.receiver_id == *receiver) {
-                        existing.weight = *weight;
-                    } else {
-                        synapses.push(Synapse {
-                            receiver_id: *receiver,
-                            weight: *weight,
-                        });
-                    }
-                    merged = merged.saturating_add(1);
-                }
-            }
-
-            if !synapses.is_empty() {
-                let avg = synapses.iter().map(|s| s.weight).sum::<f32>() / synapses.len() as f32;
-                let threshold = avg * PRUNE_RATIO;
-                let before = synapses.len();
-                synapses.retain(|s| s.weight >= threshold);
-                synapses.sort_by(|a, b| b.weight.partial_cmp(&a.weight).unwrap_or(std::cmp::Ordering::Equal));
-                pruned = pruned.saturating_add((before - synapses.len()) as u32);
-            }
-
-            if let Some(meta) = self.node_index.get_mut(sender) {
-                meta.synapse_count = synapses.len() as u32;
-            }
-        }
-
-        self.rebuild_base_bin();
+        let merged: u32 = self
+            .delta_index
+            .values()
+            .map(|m| m.len() as u32)
+            .fold(0_u32, |acc, n| acc.saturating_add(n));
+
+        self.flush_deferred();
+        let mut report = self.rebuild_base_bin();
+        report.merged = merged;
+        let pruned = report.pruned;
+        self.last_consolidation = report;
         self.delta_index.clear();
         self.reset_delta_file();
         self.temporal_window.clear();
+        self.stdp_tick = None;
         self.activation.clear();
+        self.trace.clear();
 
         // Keep only refreshed pinned hotset after major merge/prune.
         self.base_cache.clear();
@@ -1836,6 +7051,7 @@ impl RagpEngine {
                     s.adjacency = adjacency;
                     s.threshold = threshold;
                     s.activation.clear();
+                    s.trace.clear();
                     s.global_queue_len = 0;
                     s.per_shard_queue_len = vec![0; s.shard_count];
                     s.ingress_paused = false;
@@ -1844,8 +7060,11 @@ impl RagpEngine {
             }
         }
 
-        println!("[Konsolidasi] merged={} pruned={}", merged, pruned);
-        (merged, pruned)
+        println!(
+            "[Konsolidasi] merged={} pruned={} strategy={}",
+            merged, pruned, self.last_consolidation.strategy
+        );
+        Ok((merged, pruned))
     }
 
     fn status(&self) -> String {
@@ -1853,6 +7072,17 @@ impl RagpEngine {
         let budget_mb = self.cache_budget_bytes as f64 / (1024.0 * 1024.0);
         let cache_mb = self.cache_bytes_est as f64 / (1024.0 * 1024.0);
         let chunk_count = self.chunk_file_starts().len();
+        let mut drive_names: Vec<&String> = self.drives.keys().collect();
+        drive_names.sort();
+        let drives = if drive_names.is_empty() {
+            "-".to_string()
+        } else {
+            drive_names
+                .iter()
+                .map(|n| format!("{}:{:.2}", n, self.drives[*n].level))
+                .collect::<Vec<_>>()
+                .join(",")
+        };
         let mut active_count = self.activation.len();
         let mut queue_len = self.async_state.global_queue_len;
         let mut guard_mode = self.async_state.guard_mode.clone();
@@ -1865,9 +7095,14 @@ impl RagpEngine {
             queue_len = snap.1;
             guard_mode = snap.2;
         }
+        let fork = match &self.fork {
+            None => format!("forks={}", Arc::strong_count(&self.base_lease) - 1),
+            Some(state) if state.closed => "fork=closed".to_string(),
+            Some(state) => format!("fork=open journal={}", state.journal.borrow().len()),
+        };
 
         format!(
-            "Nodes={} | Chunks={} | Delta nodes={} entries={} | Active={} | Tick={} | reg_ver={} | pinned_nodes={} | lru_nodes={} | cache_budget_mb={:.1} | cache_bytes_est_mb={:.1} | async_on={} | shards={} | global_queue_len={} | guard_mode={}",
+            "Nodes={} | Chunks={} | Delta nodes={} entries={} | Active={} | Tick={} | reg_ver={} | pinned_nodes={} | lru_nodes={} | cache_budget_mb={:.1} | cache_bytes_est_mb={:.1} | async_on={} | shards={} | global_queue_len={} | guard_mode={} | drives={} | evictions={} | learned_nodes={} | concepts={} | peeks={} | {}",
             self.node_index.len(),
             chunk_count,
             self.delta_index.len(),
@@ -1882,7 +7117,13 @@ impl RagpEngine {
             self.async_state.enabled,
             self.async_state.shard_count,
             queue_len,
-            guard_mode
+            guard_mode,
+            drives,
+            self.evictions,
+            self.node_index.keys().filter(|id| **id >= LEARNED_NODE_ID_START).count(),
+            self.concepts.len(),
+            self.peeks.load(Ordering::Relaxed),
+            fork
         )
     }
 
@@ -1917,35 +7158,32 @@ async fn shard_actor_loop(
                 reply,
             } => {
                 decrement_queue_on_pop(shard_id, &shared).await;
-                process_seed_message(
-                    shard_id,
+                let msg = SeedMessage {
                     node_id,
                     strength,
                     origin_tick,
-                    Some(source),
-                    &shard_txs,
-                    &shared,
-                )
-                .await;
+                    depth: 0,
+                    source: Some(source),
+                };
+                process_seed_message(shard_id, msg, &shard_txs, &shared).await;
                 let _ = reply.send(true);
             }
             ShardCommand::Hop {
                 node_id,
                 strength,
                 origin_tick,
+                depth,
                 source_shard: _,
             } => {
                 decrement_queue_on_pop(shard_id, &shared).await;
-                process_seed_message(
-                    shard_id,
+                let msg = SeedMessage {
                     node_id,
                     strength,
                     origin_tick,
-                    None,
-                    &shard_txs,
-                    &shared,
-                )
-                .await;
+                    depth,
+                    source: None,
+                };
+                process_seed_message(shard_id, msg, &shard_txs, &shared).await;
             }
             ShardCommand::UpdateEdge {
                 sender,
@@ -1981,57 +7219,139 @@ async fn decrement_queue_on_pop(shard_id: usize, shared: &Arc<TokioMutex<AsyncSh
     }
 }
 
-async fn process_seed_message(
-    shard_id: usize,
+/// One unit of shard work: a fresh stimulus (`source` set) or a cross-shard hop
+/// continuing a wave at `depth`.
+struct SeedMessage {
     node_id: u64,
     strength: f32,
-    _origin_tick: u64,
-    _source: Option<String>,
+    origin_tick: u64,
+    depth: u8,
+    source: Option<String>,
+}
+
+async fn process_seed_message(
+    shard_id: usize,
+    msg: SeedMessage,
     shard_txs: &[mpsc::UnboundedSender<ShardCommand>],
     shared: &Arc<TokioMutex<AsyncShared>>,
 ) {
+    let SeedMessage {
+        node_id,
+        strength,
+        origin_tick,
+        depth: start_depth,
+        source,
+    } = msg;
+    let mut seed_level = strength.clamp(0.0, 1.0);
+    let (integration, tracing) = {
+        let mut s = shared.lock().await;
+        let dynamics = s.dynamics.clone();
+        if source.is_some() {
+            // Fresh stimulus (not a cross-shard hop): same seed semantics as
+            // spread_seeds. Stimuli sharing an origin tick form one wave.
+            let new_wave = s.last_wave_tick != Some(origin_tick);
+            if new_wave {
+                if dynamics.accumulates() {
+                    let elapsed = origin_tick.saturating_sub(s.activation_tick);
+                    let st = &mut *s;
+                    let ticks = elapsed as f32;
+                    decay_activation_map(&mut st.activation, &mut st.trace, ticks, &dynamics);
+                    s.activation_tick = s.activation_tick.max(origin_tick);
+                } else {
+                    s.activation.clear();
+                    s.trace.clear();
+                    s.activation_tick = origin_tick;
+                }
+                s.last_wave_tick = Some(origin_tick);
+                s.wave_seq = s.wave_seq.saturating_add(1);
+                let horizon = origin_tick.saturating_sub(ASYNC_WAVE_RETENTION);
+                s.waves.retain(|tick, _| *tick >= horizon);
+            }
+            let shared_state = &mut *s;
+            let wave = shared_state.waves.entry(origin_tick).or_default();
+            let external = seed_level;
+            seed_level = wave.seed(
+                &mut shared_state.activation,
+                node_id,
+                seed_level,
+                dynamics.integration,
+            );
+            if shared_state.trace_enabled {
+                shared_state.trace.insert(
+                    node_id,
+                    ActivationTrace::seed(external, seed_level, origin_tick, Some(shard_id)),
+                );
+            }
+        }
+        (dynamics.integration, s.trace_enabled)
+    };
+
     let mut queue: VecDeque<(u64, f32, u8)> = VecDeque::new();
-    queue.push_back((node_id, strength.max(0.0).min(1.0), 0));
+    queue.push_back((node_id, seed_level, start_depth));
 
     while let Some((node, node_strength, depth)) = queue.pop_front() {
-        if depth >= MAX_SPREAD_DEPTH {
-            continue;
-        }
-
-        let (connections, threshold_map, shard_count) = {
+        let (connections, threshold_map, shard_count, max_depth, default_threshold) = {
             let s = shared.lock().await;
             (
                 s.adjacency.get(&node).cloned().unwrap_or_default(),
                 s.threshold.clone(),
                 s.shard_count,
+                s.max_spread_depth,
+                s.default_threshold,
             )
         };
+        if depth >= max_depth {
+            continue;
+        }
 
         for syn in connections {
             let incoming = node_strength * syn.weight;
             let threshold = threshold_map
                 .get(&syn.receiver_id)
                 .copied()
-                .unwrap_or(DEFAULT_THRESHOLD);
-            if incoming < threshold {
-                continue;
-            }
-
-            {
-                let mut s = shared.lock().await;
-                let slot = s.activation.entry(syn.receiver_id).or_insert(0.0);
-                if incoming > *slot {
-                    *slot = incoming;
-                } else {
-                    continue;
-                }
-            }
+                .unwrap_or(default_threshold);
 
             let target_shard = if shard_count == 0 {
                 0
             } else {
                 (syn.receiver_id as usize) % shard_count
             };
+            let fired = {
+                let mut s = shared.lock().await;
+                let shared_state = &mut *s;
+                let wave = shared_state.waves.entry(origin_tick).or_default();
+                let fired = wave.integrate(
+                    &mut shared_state.activation,
+                    node,
+                    syn.receiver_id,
+                    incoming,
+                    threshold,
+                    integration,
+                );
+                if !shared_state.activation.contains_key(&syn.receiver_id) {
+                    shared_state.trace.remove(&syn.receiver_id);
+                }
+                if let (Some(level), true) = (fired, tracing) {
+                    shared_state.trace.insert(
+                        syn.receiver_id,
+                        ActivationTrace {
+                            predecessor: Some(node),
+                            weight: syn.weight,
+                            incoming,
+                            level,
+                            depth: depth.saturating_add(1),
+                            tick: origin_tick,
+                            shard: Some(shard_id),
+                            via_hop: target_shard != shard_id,
+                        },
+                    );
+                }
+                fired
+            };
+            let Some(incoming) = fired else {
+                continue;
+            };
+
             if target_shard == shard_id {
                 queue.push_back((syn.receiver_id, incoming, depth.saturating_add(1)));
             } else {
@@ -2046,7 +7366,8 @@ async fn process_seed_message(
                 let _ = shard_txs[target_shard].send(ShardCommand::Hop {
                     node_id: syn.receiver_id,
                     strength: incoming,
-                    origin_tick: 0,
+                    origin_tick,
+                    depth: depth.saturating_add(1),
                     source_shard: shard_id,
                 });
             }
@@ -2074,6 +7395,35 @@ async fn process_seed_message(
     }
 }
 
+/// Exponential decay: each level is multiplied by `(1 - decay)^ticks`, then
+/// levels under the floor are dropped together with their trace entries.
+fn decay_activation_map(
+    activation: &mut HashMap<u64, f32>,
+    trace: &mut HashMap<u64, ActivationTrace>,
+    ticks: f32,
+    dynamics: &ActivationDynamics,
+) {
+    if ticks <= 0.0 {
+        return;
+    }
+    let factor = (1.0 - dynamics.decay).max(0.0).powf(ticks);
+    for level in activation.values_mut() {
+        *level *= factor;
+    }
+    activation.retain(|_, level| *level >= dynamics.floor);
+    trace.retain(|node, _| activation.contains_key(node));
+}
+
+fn rng_seed_from_clock() -> u64 {
+    use std::time::{SystemTime, UNIX_EPOCH};
+
+    let nanos = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_nanos() as u64;
+    (nanos ^ 0x9E37_79B9_7F4A_7C15).max(1)
+}
+
 fn rand_f32() -> f32 {
     use std::time::{SystemTime, UNIX_EPOCH};
 
@@ -2086,7 +7436,209 @@ fn rand_f32() -> f32 {
 }
 
 #[pymodule]
-fn ctn_engine(_py: Python, m: &PyModule) -> PyResult<()> {
+fn ctn_engine(m: &Bound<'_, PyModule>) -> PyResult<()> {
     m.add_class::<RagpEngine>()?;
+    m.add_class::<EngineConfig>()?;
+    m.add_class::<QueryView>()?;
+    m.add_class::<ActivationContext>()?;
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// In-memory engine over nodes `1..=n`. The interpreter is started for the
+    /// calls that build Python return values.
+    fn engine(n: u64) -> RagpEngine {
+        pyo3::prepare_freethreaded_python();
+        let mut e = RagpEngine::in_memory(None).unwrap();
+        e.ensure_innate_registry((1..=n).collect(), None).unwrap();
+        e
+    }
+
+    fn weight(e: &mut RagpEngine, sender: u64, receiver: u64) -> Option<f32> {
+        e.current_edge_for(sender, receiver).map(|(w, _)| w)
+    }
+
+    #[test]
+    fn stdp_potentiates_pre_to_post_once_per_new_spike() {
+        let mut e = engine(3);
+        e.set_learning_mode("stdp".into(), None, None, None, None).unwrap();
+        e.update_weight(1, 2, 0.3, None).unwrap();
+        e.update_weight(2, 1, 0.3, None).unwrap();
+        e.push_window(1, 1.0);
+        e.tick += 1;
+        e.push_window(2, 1.0);
+        e.tick += 1;
+
+        e.form_synapses_from_window(None).unwrap();
+        let forward = weight(&mut e, 1, 2).unwrap();
+        let backward = weight(&mut e, 2, 1).unwrap();
+        assert!(forward > 0.3, "1->2 should be potentiated, got {}", forward);
+        assert!(backward < 0.3, "2->1 should be depressed, got {}", backward);
+
+        for _ in 0..5 {
+            e.form_synapses_from_window(None).unwrap();
+        }
+        assert_eq!(weight(&mut e, 1, 2), Some(forward));
+        assert_eq!(weight(&mut e, 2, 1), Some(backward));
+
+        e.push_window(2, 1.0);
+        e.form_synapses_from_window(None).unwrap();
+        assert!(weight(&mut e, 1, 2).unwrap() > forward);
+    }
+
+    #[test]
+    fn config_file_keeps_only_keys_set_by_setters() {
+        let mut e = engine(3);
+        assert!(!e.storage.exists(CONFIG_FILE_NAME));
+        e.set_prune_strategy("top_k:3".into(), None).unwrap();
+        let text = e.storage.read_to_string(CONFIG_FILE_NAME).unwrap();
+        let stored: toml::Table = text.parse().unwrap();
+        assert_eq!(stored.keys().collect::<Vec<_>>(), vec!["prune_strategy"]);
+    }
+
+    #[test]
+    fn drive_level_scales_seed_and_is_written_through() {
+        let mut e = engine(3);
+        e.update_weight(1, 2, 0.8, None).unwrap();
+        e.set_drive("lapar".into(), 0.5, Some(1)).unwrap();
+        e.spread_activation(1, 1.0, None).unwrap();
+        assert_eq!(e.activation.get(&1).copied(), Some(0.5));
+        assert_eq!(e.peek_spread_levels(&[(1, 1.0)], Integration::Max).get(&1), Some(&0.5));
+
+        e.set_drive("lapar".into(), 0.25, None).unwrap();
+        let text = e.storage.read_to_string(DRIVES_FILE_NAME).unwrap();
+        let stored: toml::Table = text.parse().unwrap();
+        assert_eq!(stored["lapar"]["level"].as_float(), Some(0.25));
+    }
+
+    #[test]
+    fn td_lambda_credits_earlier_step_through_its_trace() {
+        let mut e = engine(3);
+        e.apply_reward(0.0, Some(vec![1]), None, None).unwrap();
+        e.apply_reward(1.0, Some(vec![2]), Some(true), None).unwrap();
+        let (earlier, last) = (e.node_value(1), e.node_value(2));
+        assert!((last - DEFAULT_TD_ALPHA).abs() < 1e-6, "V(2) = {}", last);
+        let decayed = last * DEFAULT_TD_GAMMA * DEFAULT_TD_LAMBDA;
+        assert!((earlier - decayed).abs() < 1e-6, "V(1) = {}", earlier);
+        assert_eq!(e.node_value(3), 0.0);
+
+        e.flush_deferred();
+        let bytes = e.storage.read(VALUES_FILE_NAME).unwrap();
+        assert_eq!(u16::from_le_bytes([bytes[4], bytes[5]]), VALUES_VERSION);
+    }
+
+    #[test]
+    fn v1_delta_upgrade_keeps_update_counts() {
+        let mut e = engine(3);
+        let mut log = MAGIC_DELTA.to_le_bytes().to_vec();
+        log.extend_from_slice(&VERSION.to_le_bytes());
+        log.extend_from_slice(&(e.registry_version as u16).to_le_bytes());
+        for (ts, w) in [(5_u32, 0.2_f32), (6, 0.3), (7, 0.4)] {
+            let mut entry = 1_u64.to_le_bytes().to_vec();
+            entry.extend_from_slice(&2_u64.to_le_bytes());
+            entry.extend_from_slice(&w.to_le_bytes());
+            entry.extend_from_slice(&ts.to_le_bytes());
+            let crc = RagpEngine::crc32(&entry);
+            entry.extend_from_slice(&crc.to_le_bytes());
+            log.extend_from_slice(&entry);
+        }
+        e.storage.write(DELTA_FILE_NAME, &log).unwrap();
+
+        for _ in 0..2 {
+            e.delta_index.clear();
+            e.load_delta_index();
+            let edge = e.delta_index[&1][&2];
+            assert_eq!((edge.updates, edge.first_ts, edge.timestamp), (3, 5, 7));
+            assert_eq!(edge.weight, 0.4);
+        }
+    }
+
+    #[test]
+    fn update_weight_rejects_a_second_kind_on_one_edge() {
+        let mut e = engine(3);
+        e.update_weight(1, 2, 0.5, None).unwrap();
+        assert!(e.update_weight(1, 2, 0.6, Some("cost".into())).is_err());
+        assert_eq!(e.current_edge_for(1, 2), Some((0.5, SynapseKind::Association)));
+
+        assert!(e.retype_edge(1, 2, "cost".into()).unwrap());
+        assert_eq!(e.current_edge_for(1, 2), Some((0.5, SynapseKind::Cost)));
+        e.update_weight(1, 2, 0.7, None).unwrap();
+        assert_eq!(e.current_edge_for(1, 2), Some((0.7, SynapseKind::Cost)));
+        assert!(!e.retype_edge(2, 1, "cost".into()).unwrap());
+    }
+
+    #[test]
+    fn evict_weakest_keeps_edges_formed_earlier_in_the_pass() {
+        let mut e = engine(4);
+        e.update_config(&[
+            ("max_synapses_per_node", ConfigValue::Int(2)),
+            ("saturation_policy", ConfigValue::Str("evict_weakest".into())),
+        ])
+        .unwrap();
+        e.update_weight(1, 2, 0.9, None).unwrap();
+        for node in [1, 3, 4] {
+            e.push_window(node, 1.0);
+        }
+
+        e.form_synapses_from_window(None).unwrap();
+        assert_eq!(weight(&mut e, 1, 3), Some(DEFAULT_INITIAL_WEIGHT));
+        assert_eq!(weight(&mut e, 1, 4), Some(DEFAULT_INITIAL_WEIGHT));
+        assert_eq!(weight(&mut e, 1, 2), None);
+        assert_eq!(e.evictions, 1);
+    }
+
+    #[test]
+    fn context_state_is_restored_after_panic_and_bound_to_its_engine() {
+        let mut e = engine(3);
+        let mut ctx = e.new_context("what-if".into());
+        let outcome = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+            e.in_context(Some(&mut ctx), |inner| {
+                inner.activation.insert(1, 1.0);
+                panic!("interrupted call");
+            })
+        }));
+        assert!(outcome.is_err());
+        assert!(e.activation.is_empty());
+        assert_eq!(ctx.state.activation.get(&1), Some(&1.0));
+
+        let other = engine(3);
+        let mut foreign = other.new_context("foreign".into());
+        assert!(e.in_context(Some(&mut foreign), |_| ()).is_err());
+        assert!(e.in_context(Some(&mut ctx), |_| ()).is_ok());
+    }
+
+    #[test]
+    fn commit_fork_merges_values_drives_and_config() {
+        let mut e = engine(3);
+        e.set_drive("lapar".into(), 0.5, Some(1)).unwrap();
+        Python::with_gil(|py| {
+            let fork = Py::new(py, e.fork().unwrap()).unwrap();
+            {
+                let mut f = fork.borrow_mut(py);
+                f.apply_reward(0.0, Some(vec![1]), None, None).unwrap();
+                f.apply_reward(1.0, Some(vec![2]), Some(true), None).unwrap();
+                f.set_drive("lapar".into(), 0.8, None).unwrap();
+                f.set_prune_strategy("top_k:3".into(), None).unwrap();
+            }
+            e.commit_fork(fork.borrow_mut(py)).unwrap();
+        });
+        assert!((e.node_value(2) - DEFAULT_TD_ALPHA).abs() < 1e-6);
+        assert!(e.node_value(1) > 0.0);
+        assert_eq!(e.drives["lapar"].level, 0.8);
+        assert_eq!(e.config.prune_strategy, "top_k:3");
+        let text = e.storage.read_to_string(CONFIG_FILE_NAME).unwrap();
+        let stored: toml::Table = text.parse().unwrap();
+        assert_eq!(stored["prune_strategy"].as_str(), Some("top_k:3"));
+
+        Python::with_gil(|py| {
+            let fork = Py::new(py, e.fork().unwrap()).unwrap();
+            fork.borrow_mut(py).set_drive("lapar".into(), 0.2, None).unwrap();
+            e.set_drive("lapar".into(), 0.3, None).unwrap();
+            assert!(e.commit_fork(fork.borrow_mut(py)).is_err());
+        });
+        assert_eq!(e.drives["lapar"].level, 0.3);
+    }
+}
@@ -0,0 +1,115 @@
+"""Round-trip check format storage RAGP (butuh ctn_engine hasil build).
+
+Menyalin ragp_storage (format v1) ke direktori sementara, membukanya dengan
+engine sekarang (upgrade ke base v3 / delta v2), menulis edge bertipe, node
+learned dan tombstone, lalu konsolidasi dan buka ulang. Hal yang sama diulang
+lewat in_memory/save_to/load_from dan commit_fork.
+"""
+import os
+import shutil
+import struct
+import sys
+import tempfile
+
+import ctn_engine
+
+SUMBER = os.path.join(os.path.dirname(os.path.abspath(__file__)), "ragp_storage")
+GAGAL = []
+
+
+def cek(label, kondisi):
+    print(f"  [{'OK' if kondisi else 'GAGAL'}] {label}")
+    if not kondisi:
+        GAGAL.append(label)
+
+
+def versi(path):
+    with open(path, "rb") as f:
+        return struct.unpack("<IH", f.read(6))[1]
+
+
+def daftar_node(engine):
+    nodes = []
+    for n in range(0, 400):
+        try:
+            engine.get_typed_connections(n)
+        except ValueError:
+            continue
+        nodes.append(n)
+    return nodes + engine.list_learned_nodes()
+
+
+def snapshot(engine, nodes):
+    return {
+        n: sorted((r, round(w, 6), k) for r, w, k in engine.get_typed_connections(n))
+        for n in nodes
+    }
+
+
+print("=" * 65)
+print(" TEST ROUND-TRIP STORAGE - v1 -> v3")
+print("=" * 65)
+
+d = tempfile.mkdtemp(prefix="ragp_roundtrip_")
+storage = os.path.join(d, "store")
+shutil.copytree(SUMBER, storage)
+cek("sumber base.bin v1", versi(os.path.join(storage, "base.bin")) == 1)
+
+print("\n[1] Buka store v1")
+e = ctn_engine.RagpEngine(storage)
+nodes = daftar_node(e)
+awal = snapshot(e, nodes)
+cek(f"{len(nodes)} node terbaca", len(nodes) > 0)
+cek("ada edge terbaca", any(awal.values()))
+cek("delta.bin di-upgrade ke v2", versi(os.path.join(storage, "delta.bin")) == 2)
+
+print("\n[2] Tulis delta: edge bertipe, node learned, tombstone")
+a, b, c = nodes[0], nodes[1], nodes[2]
+e.update_weight(a, b, 0.42, "cost")
+e.update_weight(b, c, -0.3)
+baru, pensiun = e.allocate_nodes(2, "internal", 0.35)["ids"]
+e.update_weight(baru, a, 0.6, "opportunity")
+e.update_weight(pensiun, a, 0.5)
+e.retire_nodes([pensiun])
+e.set_prune_strategy("none")
+nodes = daftar_node(e)
+sebelum = snapshot(e, nodes)
+del e
+
+e = ctn_engine.RagpEngine(storage)
+cek("buka ulang dari delta identik", snapshot(e, daftar_node(e)) == sebelum)
+
+print("\n[3] Konsolidasi ke base v3 lalu buka ulang")
+e.consolidate()
+cek("base.bin v3 setelah konsolidasi", versi(os.path.join(storage, "base.bin")) == 3)
+cek("isi sama setelah konsolidasi", snapshot(e, nodes) == sebelum)
+del e
+e = ctn_engine.RagpEngine(storage)
+cek("isi sama setelah buka ulang", snapshot(e, daftar_node(e)) == sebelum)
+cek("node learned bertahan", e.list_learned_nodes() == [baru])
+
+print("\n[4] Fork: commit ke parent lalu buka ulang")
+f = e.fork()
+f.update_weight(a, c, 0.25, "transition")
+e.commit_fork(f)
+sesudah_fork = snapshot(e, nodes)
+cek("edge dari fork masuk parent", (c, 0.25, "transition") in sesudah_fork[a])
+del e
+e = ctn_engine.RagpEngine(storage)
+cek("edge dari fork bertahan setelah buka ulang", snapshot(e, nodes) == sesudah_fork)
+del e
+
+print("\n[5] In-memory: load_from -> konsolidasi -> save_to -> buka dari disk")
+m = ctn_engine.RagpEngine.load_from(storage)
+cek("load_from identik", snapshot(m, nodes) == sesudah_fork)
+m.update_weight(b, a, 0.77, "association")
+m.consolidate()
+isi_memori = snapshot(m, nodes)
+salinan = os.path.join(d, "salinan")
+m.save_to(salinan)
+cek("direktori asal tidak berubah", snapshot(ctn_engine.RagpEngine(storage), nodes) == sesudah_fork)
+cek("save_to dibuka ulang identik", snapshot(ctn_engine.RagpEngine(salinan), nodes) == isi_memori)
+
+shutil.rmtree(d, ignore_errors=True)
+print("\n" + ("SEMUA OK" if not GAGAL else f"{len(GAGAL)} GAGAL: {GAGAL}"))
+sys.exit(1 if GAGAL else 0)